@@ -1,7 +1,7 @@
 extern crate env_logger;
 extern crate sentry_rs;
 
-use sentry_rs::models::SentryCredentials;
+use sentry_rs::models::{Scheme, SentryCredentials};
 use sentry_rs::Sentry;
 use std::env;
 use std::sync::Arc;
@@ -10,9 +10,9 @@ use std::thread;
 fn main() {
   env_logger::init();
   let credentials = SentryCredentials {
-    scheme: env::var("SENTRY_SCHEME").unwrap_or("https".to_owned()),
+    scheme: env::var("SENTRY_SCHEME").ok().and_then(|scheme| scheme.parse().ok()).unwrap_or(Scheme::Https),
     key: env::var("SENTRY_KEY").unwrap_or("XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX".to_owned()),
-    secret: env::var("SENTRY_SECRET").unwrap_or("YYYYYYYYYYYYYYYYYYYYYYYYYYYYYYY".to_owned()),
+    secret: env::var("SENTRY_SECRET").ok(),
     host: Some(env::var("SENTRY_HOST").unwrap_or("app.getsentry.com".to_owned())),
     project_id: env::var("SENTRY_PROJECT_ID").unwrap_or("XX".to_owned()),
   };
@@ -0,0 +1,38 @@
+extern crate env_logger;
+#[macro_use]
+extern crate log;
+extern crate sentry_rs;
+
+use log::LevelFilter;
+use sentry_rs::logger::SentryLogger;
+use sentry_rs::models::{Scheme, SentryCredentials};
+use sentry_rs::Sentry;
+use std::env;
+use std::sync::Arc;
+
+fn main() {
+  let credentials = SentryCredentials {
+    scheme: env::var("SENTRY_SCHEME").ok().and_then(|scheme| scheme.parse().ok()).unwrap_or(Scheme::Https),
+    key: env::var("SENTRY_KEY").unwrap_or("XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX".to_owned()),
+    secret: env::var("SENTRY_SECRET").ok(),
+    host: Some(env::var("SENTRY_HOST").unwrap_or("app.getsentry.com".to_owned())),
+    project_id: env::var("SENTRY_PROJECT_ID").unwrap_or("XX".to_owned()),
+  };
+  let sentry = Arc::new(Sentry::new(
+    "Server Name".to_string(),
+    "Release of Your Project Consider using env!()".to_string(),
+    "Environment you're deployed in".to_string(),
+    credentials,
+  ));
+
+  // Keep logging to the console through env_logger, while also sending records to Sentry.
+  let env_logger = env_logger::Builder::from_default_env().build();
+  let env_level = env_logger.filter();
+  SentryLogger::new(sentry, LevelFilter::Warn)
+    .init_with_inner(env_logger, env_level)
+    .expect("Failed to install the sentry logger!");
+
+  // Anything at warn or above is sent to Sentry, with this modules path as the logger.
+  warn!("Something looks off");
+  error!("Something went wrong");
+}
@@ -0,0 +1,119 @@
+//! Builds the `X-Sentry-Auth` header every request to Sentry is sent with, so anything sending to Sentry
+//! authenticates the same way the client does.
+//!
+//! ```text
+//! Sentry sentry_version=7,sentry_client=sentry-rs/2.2.0,sentry_timestamp=1500000000.250,sentry_key=<key>
+//! ```
+//!
+//! The timestamp and secret key are only included when they're set.
+
+use chrono::{DateTime, Utc};
+
+use std::fmt;
+
+/// The version of Sentry's protocol events are sent with.
+pub const PROTOCOL_VERSION: u32 = 7;
+
+/// The client events are sent by, with this crate's version.
+pub const CLIENT: &'static str = concat!("sentry-rs/", env!("CARGO_PKG_VERSION"));
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+/// The `X-Sentry-Auth` header, written out by its `Display`.
+///
+/// # Examples
+///
+/// ```rust
+/// extern crate chrono;
+/// extern crate sentry_rs;
+///
+/// use chrono::{TimeZone, Utc};
+/// use sentry_rs::auth::SentryAuth;
+///
+/// fn main() {
+///   let auth = SentryAuth::new("public")
+///     .client("my-transport/1.0")
+///     .timestamp(Utc.timestamp_millis_opt(1_500_000_000_250).unwrap())
+///     .secret("private");
+///   assert_eq!(
+///     auth.to_string(),
+///     "Sentry sentry_version=7,sentry_client=my-transport/1.0,sentry_timestamp=1500000000.250,\
+///      sentry_key=public,sentry_secret=private"
+///   );
+/// }
+/// ```
+pub struct SentryAuth {
+  /// The version of Sentry's protocol, `PROTOCOL_VERSION` by default.
+  pub version: u32,
+  /// The name and version of the client sending, `CLIENT` by default.
+  pub client: String,
+  /// When the request was sent, which Sentry compares with when it arrives to correct for a client whose
+  /// clock is off.
+  pub timestamp: Option<DateTime<Utc>>,
+  /// The project's public key.
+  pub key: String,
+  /// The project's secret key, which current versions of Sentry don't need.
+  pub secret: Option<String>,
+}
+
+impl SentryAuth {
+  /// Authenticates with the public `key`, as this crate on the current protocol version, without a
+  /// timestamp or secret key.
+  pub fn new(key: &str) -> SentryAuth {
+    SentryAuth {
+      version: PROTOCOL_VERSION,
+      client: CLIENT.to_owned(),
+      timestamp: None,
+      key: key.to_owned(),
+      secret: None,
+    }
+  }
+
+  /// Sets the version of Sentry's protocol.
+  pub fn version(mut self, version: u32) -> SentryAuth {
+    self.version = version;
+    self
+  }
+
+  /// Sets the name and version of the client, such as `my-transport/1.0`.
+  pub fn client(mut self, client: &str) -> SentryAuth {
+    self.client = client.to_owned();
+    self
+  }
+
+  /// Sets when the request was sent.
+  pub fn timestamp(mut self, timestamp: DateTime<Utc>) -> SentryAuth {
+    self.timestamp = Some(timestamp);
+    self
+  }
+
+  /// Sets the public key.
+  pub fn key(mut self, key: &str) -> SentryAuth {
+    self.key = key.to_owned();
+    self
+  }
+
+  /// Sets the secret key.
+  pub fn secret(mut self, secret: &str) -> SentryAuth {
+    self.secret = Some(secret.to_owned());
+    self
+  }
+}
+
+impl fmt::Display for SentryAuth {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "Sentry sentry_version={},sentry_client={}", self.version, self.client)?;
+    if let Some(timestamp) = self.timestamp {
+      write!(
+        f,
+        ",sentry_timestamp={}.{:03}",
+        timestamp.timestamp(),
+        timestamp.timestamp_subsec_millis()
+      )?;
+    }
+    write!(f, ",sentry_key={}", self.key)?;
+    if let Some(ref secret) = self.secret {
+      write!(f, ",sentry_secret={}", secret)?;
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,81 @@
+//! Sends a test event to the DSN in `SENTRY_DSN`, through the same queue and worker as any other event,
+//! and waits for Sentry to accept it. Prints the event's id, or why it wasn't sent.
+//!
+//! For checking a DSN, and that a host can reach Sentry, before relying on it:
+//!
+//! ```text
+//! SENTRY_DSN=https://key@sentry.example.com/1 sentry-rs-send-test "Hello from a new host"
+//! ```
+//!
+//! `SENTRY_RELEASE` and `SENTRY_ENVIRONMENT` set the event's release and environment, and
+//! `SENTRY_TIMEOUT` how many seconds to wait for it (10 by default). Set `SENTRY_RS_DEBUG=debug` to see
+//! what the SDK is doing.
+
+extern crate sentry_rs;
+
+use sentry_rs::Sentry;
+use sentry_rs::future::{DeliveryHandle, DeliveryStatus};
+use sentry_rs::models::SentryCredentials;
+
+use std::env;
+use std::process;
+use std::time::Duration;
+
+fn main() {
+  let dsn = match env::var("SENTRY_DSN") {
+    Ok(dsn) => dsn,
+    Err(_) => {
+      eprintln!("SENTRY_DSN isn't set.");
+      process::exit(2);
+    }
+  };
+  let credentials: SentryCredentials = match dsn.parse() {
+    Ok(credentials) => credentials,
+    Err(err) => {
+      eprintln!("SENTRY_DSN isn't a valid DSN: {}", err);
+      process::exit(2);
+    }
+  };
+  let timeout = match env::var("SENTRY_TIMEOUT").ok().map(|secs| secs.parse::<u64>()) {
+    None => Duration::from_secs(10),
+    Some(Ok(secs)) => Duration::from_secs(secs),
+    Some(Err(_)) => {
+      eprintln!("SENTRY_TIMEOUT isn't a whole number of seconds.");
+      process::exit(2);
+    }
+  };
+  let message = env::args()
+    .nth(1)
+    .unwrap_or("Test event from sentry-rs-send-test".to_owned());
+
+  let sentry = Sentry::new(
+    env::var("HOSTNAME").unwrap_or("sentry-rs-send-test".to_owned()),
+    env::var("SENTRY_RELEASE").unwrap_or(env!("CARGO_PKG_VERSION").to_owned()),
+    env::var("SENTRY_ENVIRONMENT").unwrap_or("test".to_owned()),
+    credentials,
+  );
+  let event = sentry.create_event("sentry-rs-send-test", "info", &message, None, None, None);
+  println!("Sending event {} to {}", event.event_id, sentry.options().credentials.destination());
+
+  let status = DeliveryHandle::from(sentry.capture_event_with_timeout(event, timeout)).wait(timeout);
+  sentry.close(Duration::from_secs(1));
+  match status {
+    DeliveryStatus::Sent(event_id) => println!("Sentry accepted event {}", event_id),
+    DeliveryStatus::Pending => {
+      eprintln!("Sentry didn't respond within {} seconds.", timeout.as_secs());
+      process::exit(1);
+    }
+    DeliveryStatus::Failed(err) => {
+      eprintln!("Sending failed: {}", err);
+      process::exit(1);
+    }
+    DeliveryStatus::Dropped(err) => {
+      eprintln!("The event was dropped before it was sent: {}", err);
+      process::exit(1);
+    }
+    DeliveryStatus::Skipped => {
+      eprintln!("The event was skipped.");
+      process::exit(1);
+    }
+  }
+}
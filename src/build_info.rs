@@ -0,0 +1,176 @@
+//! Describes the build of the program sending events, its git commit and whether it had uncommitted
+//! changes, when it was built, and for which target and profile, sent with every event once set with
+//! `Sentry::set_build_info`.
+//!
+//! Cargo only tells a crate's build script about that crate's own build, so the program's build script
+//! calls `emit`, with `sentry-rs` as a build dependency, and `sentry_build_info!` reads what it emitted
+//! back when the program is compiled:
+//!
+//! ```rust,ignore
+//! // build.rs
+//! extern crate sentry_rs;
+//!
+//! fn main() {
+//!   sentry_rs::build_info::emit();
+//! }
+//! ```
+//!
+//! ```rust,ignore
+//! // main.rs
+//! #[macro_use]
+//! extern crate sentry_rs;
+//!
+//! fn main() {
+//!   let sentry = ...;
+//!   sentry.set_build_info(sentry_build_info!());
+//! }
+//! ```
+//!
+//! Anything `emit` couldn't find out, such as the commit when the program isn't built from a git
+//! checkout, is left out.
+
+use models::Event;
+use serde_json::Value;
+
+use chrono::Utc;
+
+use std::env;
+use std::process::Command;
+
+/// The variable the git commit is emitted as.
+pub const GIT_COMMIT_VAR: &'static str = "SENTRY_RS_GIT_COMMIT";
+/// The variable whether the checkout had uncommitted changes is emitted as, `true` or `false`.
+pub const GIT_DIRTY_VAR: &'static str = "SENTRY_RS_GIT_DIRTY";
+/// The variable the time of the build is emitted as.
+pub const BUILD_TIMESTAMP_VAR: &'static str = "SENTRY_RS_BUILD_TIMESTAMP";
+/// The variable the target triple is emitted as.
+pub const BUILD_TARGET_VAR: &'static str = "SENTRY_RS_BUILD_TARGET";
+/// The variable the profile is emitted as.
+pub const BUILD_PROFILE_VAR: &'static str = "SENTRY_RS_BUILD_PROFILE";
+
+/// The build of the program being compiled, as emitted by its build script with `emit`.
+///
+/// # Examples
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate sentry_rs;
+///
+/// fn main() {
+///   // Without a build script calling `emit`, nothing is known about the build.
+///   let build = sentry_build_info!();
+///   assert_eq!(build.git_commit, None);
+/// }
+/// ```
+#[macro_export]
+macro_rules! sentry_build_info {
+  () => {
+    $crate::build_info::BuildInfo::from_emitted(
+      option_env!("SENTRY_RS_GIT_COMMIT"),
+      option_env!("SENTRY_RS_GIT_DIRTY"),
+      option_env!("SENTRY_RS_BUILD_TIMESTAMP"),
+      option_env!("SENTRY_RS_BUILD_TARGET"),
+      option_env!("SENTRY_RS_BUILD_PROFILE"),
+    )
+  };
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+/// How the program sending events was built. Sent as the `git_commit`, `git_dirty`, `build_target`, and
+/// `build_profile` tags, and the `build_timestamp` extra.
+pub struct BuildInfo {
+  /// The git commit the program was built from.
+  pub git_commit: Option<String>,
+  /// Whether the checkout had changes that weren't committed.
+  pub git_dirty: Option<bool>,
+  /// When the program was built, in UTC, such as `2018-03-01T12:00:00Z`.
+  pub build_timestamp: Option<String>,
+  /// The target triple the program was built for, such as `x86_64-unknown-linux-gnu`.
+  pub build_target: Option<String>,
+  /// The profile the program was built with, `debug` or `release`.
+  pub build_profile: Option<String>,
+}
+
+impl BuildInfo {
+  /// Reads what `emit` emitted, as `sentry_build_info!` finds it. Empty values are left out.
+  pub fn from_emitted(
+    git_commit: Option<&str>,
+    git_dirty: Option<&str>,
+    build_timestamp: Option<&str>,
+    build_target: Option<&str>,
+    build_profile: Option<&str>,
+  ) -> BuildInfo {
+    let value = |value: Option<&str>| value.filter(|value| !value.is_empty()).map(String::from);
+    BuildInfo {
+      git_commit: value(git_commit),
+      git_dirty: git_dirty.and_then(|dirty| dirty.parse().ok()),
+      build_timestamp: value(build_timestamp),
+      build_target: value(build_target),
+      build_profile: value(build_profile),
+    }
+  }
+
+  /// Adds the build to `event`, unless it already has tags or extras with the same names.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use sentry_rs::build_info::BuildInfo;
+  /// use sentry_rs::models::Event;
+  ///
+  /// let build = BuildInfo::from_emitted(Some("9fceb02"), Some("false"), None, None, Some("release"));
+  /// let mut event = Event::new("app", "error", "Crashed", None, None, None, None, None, None, None);
+  /// build.apply(&mut event);
+  /// assert_eq!(event.tags["git_commit"], "9fceb02");
+  /// assert_eq!(event.tags["git_dirty"], "false");
+  /// assert!(!event.tags.contains_key("build_target"));
+  /// ```
+  pub fn apply(&self, event: &mut Event) {
+    let tags = vec![
+      ("git_commit", self.git_commit.clone()),
+      ("git_dirty", self.git_dirty.map(|dirty| dirty.to_string())),
+      ("build_target", self.build_target.clone()),
+      ("build_profile", self.build_profile.clone()),
+    ];
+    for (name, value) in tags {
+      if let Some(value) = value {
+        event.tags.entry(name.to_owned()).or_insert(value);
+      }
+    }
+    if let Some(ref timestamp) = self.build_timestamp {
+      event
+        .extra
+        .entry("build_timestamp".to_owned())
+        .or_insert_with(|| Value::String(timestamp.clone()));
+    }
+  }
+}
+
+/// Emits the build of the crate whose build script calls it, for `sentry_build_info!` to read. Only
+/// call this from a build script.
+///
+/// The commit and whether there are uncommitted changes come from running `git` in the crate's
+/// directory, and the target and profile from what Cargo tells the build script.
+pub fn emit() {
+  let git_commit = git(&["rev-parse", "HEAD"]);
+  let git_dirty = git(&["status", "--porcelain"]).map(|status| (!status.is_empty()).to_string());
+  let vars = vec![
+    (GIT_COMMIT_VAR, git_commit),
+    (GIT_DIRTY_VAR, git_dirty),
+    (BUILD_TIMESTAMP_VAR, Some(Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string())),
+    (BUILD_TARGET_VAR, env::var("TARGET").ok()),
+    (BUILD_PROFILE_VAR, env::var("PROFILE").ok()),
+  ];
+  for (name, value) in vars {
+    println!("cargo:rustc-env={}={}", name, value.unwrap_or_default());
+  }
+}
+
+/// The trimmed output of `git` run with `args`, if it ran successfully.
+fn git(args: &[&str]) -> Option<String> {
+  let output = Command::new("git").args(args).output().ok()?;
+  if !output.status.success() {
+    return None;
+  }
+  Some(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
@@ -0,0 +1,211 @@
+//! Entry points shaped like the official `sentry` crate's, so a project moving between the two SDKs only
+//! has to change its imports. Events still go through this crate's client, queue, and transport.
+//!
+//! `init` creates a client from a DSN and `ClientOptions`, binds it to the main hub, and reports panics
+//! to it. `capture_message` and `capture_error` then send through the current hub, the way
+//! `sentry::capture_message` and `sentry::capture_error` do:
+//!
+//! ```rust,no_run
+//! extern crate sentry_rs;
+//!
+//! use sentry_rs::compat::{self as sentry, ClientOptions, Level};
+//!
+//! fn main() {
+//!   let _guard = sentry::init((
+//!     "https://key@sentry.example.com/1",
+//!     ClientOptions {
+//!       release: Some("my-app@1.0.0".to_owned()),
+//!       ..Default::default()
+//!     },
+//!   ));
+//!   sentry::capture_message("Started", Level::Info);
+//!   if let Err(err) = "ten".parse::<u32>() {
+//!     sentry::capture_error(&err);
+//!   }
+//! }
+//! ```
+//!
+//! Unlike the official crate, a DSN that can't be parsed doesn't panic: it's logged, and the returned
+//! guard is disabled, as it is without a DSN.
+
+use Sentry;
+use config::SentryConfig;
+use hub::Hub;
+use models::EventId;
+
+pub use models::Level;
+pub use scope::configure_scope;
+
+use std::env;
+use std::error::Error as StdError;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The variable the DSN is read from when `ClientOptions::dsn` isn't set.
+pub const DSN_VAR: &'static str = "SENTRY_DSN";
+
+/// How long dropping a `ClientInitGuard` waits for queued events to be sent.
+pub const CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Debug, PartialEq)]
+/// The options `init` creates a client with, named as in the official crate's `ClientOptions`. This isn't
+/// `request::ClientOptions`, which sets up the HTTP client.
+pub struct ClientOptions {
+  /// Where events are sent. Read from `SENTRY_DSN` when not set, and nothing is sent without either.
+  pub dsn: Option<String>,
+  /// The release events are sent as. Defaults to none.
+  pub release: Option<String>,
+  /// The environment events are sent as. Defaults to `production`.
+  pub environment: Option<String>,
+  /// The server events are sent from. Defaults to none.
+  pub server_name: Option<String>,
+  /// The distribution of the release events are sent as.
+  pub dist: Option<String>,
+  /// The fraction of logged events that are sent, from `0.0` to `1.0`. Defaults to `1.0`.
+  pub sample_rate: f32,
+}
+
+impl Default for ClientOptions {
+  fn default() -> ClientOptions {
+    ClientOptions {
+      dsn: None,
+      release: None,
+      environment: None,
+      server_name: None,
+      dist: None,
+      sample_rate: 1.0,
+    }
+  }
+}
+
+impl<'a> From<&'a str> for ClientOptions {
+  fn from(dsn: &'a str) -> ClientOptions {
+    ClientOptions {
+      dsn: Some(dsn.to_owned()),
+      ..Default::default()
+    }
+  }
+}
+
+impl From<String> for ClientOptions {
+  fn from(dsn: String) -> ClientOptions {
+    ClientOptions {
+      dsn: Some(dsn),
+      ..Default::default()
+    }
+  }
+}
+
+impl<'a> From<(&'a str, ClientOptions)> for ClientOptions {
+  fn from((dsn, options): (&'a str, ClientOptions)) -> ClientOptions {
+    ClientOptions {
+      dsn: Some(dsn.to_owned()),
+      ..options
+    }
+  }
+}
+
+impl From<(String, ClientOptions)> for ClientOptions {
+  fn from((dsn, options): (String, ClientOptions)) -> ClientOptions {
+    ClientOptions {
+      dsn: Some(dsn),
+      ..options
+    }
+  }
+}
+
+impl ClientOptions {
+  /// The configuration a client is built from, with the DSN read from `SENTRY_DSN` when it isn't set.
+  /// `None` when there's no DSN, or it's empty.
+  pub fn config(&self) -> Option<SentryConfig> {
+    let dsn = self.dsn.clone().or_else(|| env::var(DSN_VAR).ok())?;
+    if dsn.is_empty() {
+      return None;
+    }
+    let mut config = SentryConfig::default();
+    config.dsn = dsn;
+    config.release = self.release.clone().unwrap_or_default();
+    if let Some(ref environment) = self.environment {
+      config.environment = environment.clone();
+    }
+    config.server_name = self.server_name.clone().unwrap_or_default();
+    config.dist = self.dist.clone();
+    config.sample_rate = self.sample_rate;
+    Some(config)
+  }
+}
+
+/// Keeps the client `init` created bound to the main hub. When it's dropped, the client is unbound and
+/// closed, waiting up to `CLOSE_TIMEOUT` for queued events to be sent. Panics are still handed to the
+/// closed client, and dropped.
+pub struct ClientInitGuard {
+  client: Option<Arc<Sentry>>,
+}
+
+impl ClientInitGuard {
+  /// Whether `init` created a client, rather than sending nothing.
+  pub fn is_enabled(&self) -> bool {
+    self.client.is_some()
+  }
+
+  /// The client `init` created, if it did.
+  pub fn client(&self) -> Option<Arc<Sentry>> {
+    self.client.clone()
+  }
+}
+
+impl Drop for ClientInitGuard {
+  fn drop(&mut self) {
+    if let Some(client) = self.client.take() {
+      for hub in &[Hub::main(), Hub::current()] {
+        if hub.client().map_or(false, |bound| Arc::ptr_eq(&bound, &client)) {
+          hub.bind_client(None);
+        }
+      }
+      client.close(CLOSE_TIMEOUT);
+    }
+  }
+}
+
+/// Creates a client from `options`, such as a DSN or a `(dsn, ClientOptions)` pair, binds it to the main
+/// hub and this thread's hub, and reports panics to it. Keep the returned guard alive for as long as
+/// events should be sent. Without a valid DSN nothing is sent, and the guard is disabled.
+pub fn init<O: Into<ClientOptions>>(options: O) -> ClientInitGuard {
+  let config = match options.into().config() {
+    Some(config) => config,
+    None => {
+      sdk_log!(Info, "No DSN was given, so no events will be sent to Sentry");
+      return ClientInitGuard { client: None };
+    }
+  };
+  let client = match config.build() {
+    Ok(client) => Arc::new(client),
+    Err(err) => {
+      sdk_log!(Warn, "Not sending events to Sentry, the options aren't valid: {}", err);
+      return ClientInitGuard { client: None };
+    }
+  };
+  client.register_panic_handler();
+  Hub::main().bind_client(Some(client.clone()));
+  Hub::current().bind_client(Some(client.clone()));
+  ClientInitGuard {
+    client: Some(client),
+  }
+}
+
+/// Sends `message` at `level` through the current hub, returning the event's id, or an id of all zeroes
+/// when nothing was sent, as the official crate returns a nil id.
+pub fn capture_message(message: &str, level: Level) -> EventId {
+  Hub::current().capture_message(message, level).unwrap_or_else(nil_event_id)
+}
+
+/// Sends `err` through the current hub, with the chain of errors that caused it, returning the event's id,
+/// or an id of all zeroes when nothing was sent.
+pub fn capture_error<E: StdError + 'static>(err: &E) -> EventId {
+  Hub::current().capture_error(err).unwrap_or_else(nil_event_id)
+}
+
+/// The id of an event that wasn't sent.
+fn nil_event_id() -> EventId {
+  "0".repeat(32)
+}
@@ -0,0 +1,296 @@
+//! Loads a client's configuration from a file, so it can live in a service's own config file rather than
+//! being assembled in code wherever a client is created.
+//!
+//! `SentryConfig` can be read from JSON, or from TOML with the `toml` feature. It implements
+//! `Deserialize`, so it can also be a section of a larger config file that's deserialized as a whole.
+//! Every field but `dsn` is optional.
+
+use filter::{ErrorPattern, FingerprintStrategy, FrameLimit, FrameTrim, LoggerLevels, PathRewrite, TagFilter};
+use models::{CredentialsParseError, Environment, EnvironmentError, Level, SentryCredentials};
+use regex;
+use snapshot::ProcessSnapshot;
+use serde_json;
+#[cfg(feature = "toml")]
+use toml;
+use {Sentry, SentryOptions};
+
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fmt;
+use std::time::Duration;
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+/// A client's configuration, as read from a file.
+///
+/// # Examples
+///
+/// ```rust
+/// use sentry_rs::config::SentryConfig;
+///
+/// let config = SentryConfig::from_json(r#"{
+///   "dsn": "https://key@sentry.example.com/1",
+///   "environment": "staging",
+///   "sample_rate": 0.5,
+///   "ignore_errors": ["Broken pipe"],
+///   "tags": { "deny": ["auth_token"] }
+/// }"#).unwrap();
+///
+/// let options = config.options().unwrap();
+/// assert_eq!(options.environment, "staging");
+/// assert_eq!(options.sample_rate, 0.5);
+/// ```
+pub struct SentryConfig {
+  /// Where events are sent.
+  pub dsn: String,
+  /// The server events are sent from. Defaults to empty.
+  pub server_name: String,
+  /// The release events are sent as. Defaults to empty.
+  pub release: String,
+  /// The distribution of the release events are sent as, such as a build for one architecture.
+  pub dist: Option<String>,
+  /// The environment events are sent as, which must be a valid `Environment`. Defaults to `production`.
+  pub environment: String,
+  /// Other projects every event is also sent to, see `SentryOptions::additional_credentials`.
+  pub additional_dsns: Vec<String>,
+  /// The fraction of logged events that are sent, from `0.0` to `1.0`. Defaults to `1.0`.
+  pub sample_rate: f32,
+  /// The least severe level logged events are sent at, see `Sentry::set_min_level`. Defaults to sending
+  /// every level.
+  pub min_level: Option<String>,
+  /// The least severe level events from particular loggers are sent at, by logger pattern, see
+  /// `LoggerLevels`.
+  pub logger_levels: HashMap<String, String>,
+  /// Logged events whose message, logger, or culprit is exactly one of these are dropped.
+  pub ignore_errors: Vec<String>,
+  /// Logged events whose message, logger, or culprit matches one of these regexes are dropped.
+  pub ignore_error_patterns: Vec<String>,
+  /// Drops and renames tags before events are sent, such as ones holding secrets.
+  pub tags: TagFilter,
+  /// Whether the panic and runtime frames are trimmed from stack traces with `FrameTrim::new`. Defaults to
+  /// `false`.
+  pub trim_frames: bool,
+  /// How many frames of each stack trace are kept at either end, such as `{ "top": 50, "bottom": 10 }`,
+  /// see `FrameLimit`. Defaults to sending every frame.
+  pub frame_limit: Option<FrameLimit>,
+  /// How many seconds breadcrumbs are sent with events for, see `Sentry::set_max_breadcrumb_age`. Defaults
+  /// to sending them however old they are.
+  pub max_breadcrumb_age_secs: Option<u64>,
+  /// Whether the paths of files in stack frames are rewritten with `PathRewrite::new`. Defaults to
+  /// `false`.
+  pub rewrite_paths: bool,
+  /// Prefixes of the paths of files in stack frames to rewrite, such as the directory the program was built
+  /// in, mapped to what they're rewritten to. These are rewritten even when `rewrite_paths` is `false`.
+  pub path_prefixes: HashMap<String, String>,
+  /// How events without a fingerprint are grouped, such as `message_template`, see `FingerprintStrategy`.
+  pub fingerprint_strategy: Option<FingerprintStrategy>,
+  /// What's taken of how the process was run for panic events, see `Sentry::set_panic_snapshot`.
+  pub panic_snapshot: Option<ProcessSnapshot>,
+  /// Whether events are checked against Sentry's limits before they're sent, see
+  /// `Sentry::set_validate_events`. Defaults to `true` in debug builds.
+  pub validate_events: Option<bool>,
+}
+
+impl Default for SentryConfig {
+  fn default() -> SentryConfig {
+    SentryConfig {
+      dsn: String::new(),
+      server_name: String::new(),
+      release: String::new(),
+      dist: None,
+      environment: "production".to_owned(),
+      additional_dsns: Vec::new(),
+      sample_rate: 1.0,
+      min_level: None,
+      logger_levels: HashMap::new(),
+      ignore_errors: Vec::new(),
+      ignore_error_patterns: Vec::new(),
+      tags: TagFilter::new(),
+      trim_frames: false,
+      frame_limit: None,
+      max_breadcrumb_age_secs: None,
+      rewrite_paths: false,
+      path_prefixes: HashMap::new(),
+      fingerprint_strategy: None,
+      panic_snapshot: None,
+      validate_events: None,
+    }
+  }
+}
+
+impl SentryConfig {
+  /// Reads a configuration from JSON.
+  pub fn from_json(json: &str) -> Result<SentryConfig, ConfigError> {
+    serde_json::from_str(json).map_err(|err| ConfigError::Parse(err.to_string()))
+  }
+
+  /// Reads a configuration from TOML.
+  #[cfg(feature = "toml")]
+  pub fn from_toml(toml: &str) -> Result<SentryConfig, ConfigError> {
+    toml::from_str(toml).map_err(|err| ConfigError::Parse(err.to_string()))
+  }
+
+  /// The options events are sent with. Fails if a DSN, the environment, the sample rate, or a pattern
+  /// isn't valid.
+  pub fn options(&self) -> Result<SentryOptions, ConfigError> {
+    let credentials = parse_dsn("dsn", &self.dsn)?;
+    let environment = Environment::new(&self.environment).map_err(ConfigError::Environment)?;
+    if !(self.sample_rate >= 0.0 && self.sample_rate <= 1.0) {
+      return Err(ConfigError::SampleRate(self.sample_rate));
+    }
+
+    let mut options = SentryOptions::new(credentials, self.release.clone(), environment.into());
+    for (i, dsn) in self.additional_dsns.iter().enumerate() {
+      options
+        .additional_credentials
+        .push(parse_dsn(&format!("additional_dsns[{}]", i), dsn)?);
+    }
+    options.dist = self.dist.clone();
+    options.sample_rate = self.sample_rate;
+    options.ignore_errors = self
+      .ignore_errors
+      .iter()
+      .map(|exact| ErrorPattern::Exact(exact.clone()))
+      .collect();
+    for pattern in &self.ignore_error_patterns {
+      options
+        .ignore_errors
+        .push(ErrorPattern::regex(pattern).map_err(ConfigError::Pattern)?);
+    }
+    Ok(options)
+  }
+
+  /// Creates a client with this configuration.
+  pub fn build(&self) -> Result<Sentry, ConfigError> {
+    let options = self.options()?;
+    let sentry = Sentry::new(
+      self.server_name.clone(),
+      options.release.clone(),
+      options.environment.clone(),
+      options.credentials.clone(),
+    );
+    self.apply(&sentry)?;
+    Ok(sentry)
+  }
+
+  /// Reconfigures a running client, such as when its config file is reloaded. Nothing is changed if the
+  /// configuration isn't valid. The server name is kept, since it's only set when a client is created, as
+  /// are a frame trim, path rewrite, tag filter, or fingerprint strategy set before when this one has none.
+  /// Routes can't be configured here, so the client's routes are kept too.
+  pub fn apply(&self, sentry: &Sentry) -> Result<(), ConfigError> {
+    let mut options = self.options()?;
+    let min_level = match self.min_level {
+      Some(ref name) => parse_level(name)?,
+      None => Level::Debug,
+    };
+    let mut logger_levels = LoggerLevels::new();
+    for (pattern, name) in &self.logger_levels {
+      logger_levels.levels.insert(pattern.clone(), parse_level(name)?);
+    }
+
+    options.routes = sentry.options().routes;
+    sentry.reconfigure(options);
+    sentry.set_min_level(min_level);
+    sentry.set_logger_levels(logger_levels);
+    if self.tags != TagFilter::new() {
+      sentry.set_tag_filter(self.tags.clone());
+    }
+    if self.trim_frames {
+      sentry.set_frame_trim(FrameTrim::new());
+    }
+    if let Some(frame_limit) = self.frame_limit {
+      sentry.set_frame_limit(frame_limit);
+    }
+    if let Some(secs) = self.max_breadcrumb_age_secs {
+      sentry.set_max_breadcrumb_age(Duration::from_secs(secs));
+    }
+    if self.rewrite_paths || !self.path_prefixes.is_empty() {
+      let mut path_rewrite = if self.rewrite_paths {
+        PathRewrite::new()
+      } else {
+        PathRewrite { rules: Vec::new() }
+      };
+      // The longest prefixes are tried first, so a directory can be rewritten differently from its parent.
+      let mut prefixes = self.path_prefixes.iter().collect::<Vec<_>>();
+      prefixes.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
+      for (prefix, replacement) in prefixes {
+        path_rewrite.add_prefix(prefix, replacement);
+      }
+      sentry.set_path_rewrite(path_rewrite);
+    }
+    if let Some(strategy) = self.fingerprint_strategy {
+      sentry.set_fingerprint_strategy(strategy);
+    }
+    if let Some(ref snapshot) = self.panic_snapshot {
+      sentry.set_panic_snapshot(Some(snapshot.clone()));
+    }
+    if let Some(validate_events) = self.validate_events {
+      sentry.set_validate_events(validate_events);
+    }
+    Ok(())
+  }
+}
+
+/// Parses the name of a level.
+fn parse_level(name: &str) -> Result<Level, ConfigError> {
+  Level::from_name(name).ok_or_else(|| ConfigError::Level(name.to_owned()))
+}
+
+/// Parses the DSN in `field`.
+fn parse_dsn(field: &str, dsn: &str) -> Result<SentryCredentials, ConfigError> {
+  dsn
+    .parse()
+    .map_err(|err| ConfigError::Credentials(field.to_owned(), err))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Why a configuration couldn't be read or used.
+pub enum ConfigError {
+  /// The file isn't valid, or doesn't fit `SentryConfig`.
+  Parse(String),
+  /// A DSN couldn't be parsed, along with the field it's in, such as `additional_dsns[1]`.
+  Credentials(String, CredentialsParseError),
+  /// The environment isn't a valid `Environment`.
+  Environment(EnvironmentError),
+  /// The sample rate isn't between `0.0` and `1.0`.
+  SampleRate(f32),
+  /// A minimum level isn't one Sentry understands.
+  Level(String),
+  /// An ignored error pattern isn't a valid regex.
+  Pattern(regex::Error),
+}
+
+impl StdError for ConfigError {
+  fn description(&self) -> &str {
+    match *self {
+      ConfigError::Parse(_) => "the configuration couldn't be parsed",
+      ConfigError::Credentials(..) => "a dsn couldn't be parsed",
+      ConfigError::Environment(ref err) => err.description(),
+      ConfigError::SampleRate(_) => "the sample rate isn't between 0 and 1",
+      ConfigError::Level(_) => "a minimum level isn't one sentry understands",
+      ConfigError::Pattern(_) => "an ignored error pattern isn't a valid regex",
+    }
+  }
+
+  fn source(&self) -> Option<&(StdError + 'static)> {
+    match *self {
+      ConfigError::Credentials(_, ref err) => Some(err),
+      ConfigError::Environment(ref err) => Some(err),
+      ConfigError::Pattern(ref err) => Some(err),
+      ConfigError::Parse(_) | ConfigError::SampleRate(_) | ConfigError::Level(_) => None,
+    }
+  }
+}
+
+impl fmt::Display for ConfigError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      ConfigError::Parse(ref message) => write!(f, "Invalid configuration: {}", message),
+      ConfigError::Credentials(ref field, ref err) => write!(f, "Invalid {}: {}", field, err),
+      ConfigError::Environment(ref err) => write!(f, "{}", err),
+      ConfigError::SampleRate(rate) => write!(f, "The sample rate {} isn't between 0 and 1", rate),
+      ConfigError::Level(ref level) => write!(f, "The level {:?} isn't one Sentry understands", level),
+      ConfigError::Pattern(ref err) => write!(f, "Invalid ignored error pattern: {}", err),
+    }
+  }
+}
@@ -0,0 +1,220 @@
+//! Keeps a record of events that never made it to Sentry, so there's something to look back on (and
+//! resubmit) after an outage.
+//!
+//! Each event is stored as a record: a header line, then the event's JSON in the same form it would have
+//! been posted in, then a newline.
+//!
+//! ```text
+//! sentry-rs-dead-letter <version> <length> <checksum>
+//! <body>
+//! ```
+//!
+//! `<version>` is the `FORMAT_VERSION` the record was written with, `<length>` the length of `<body>` in
+//! bytes, and `<checksum>` the CRC-32 of `<body>` as 8 lowercase hex digits. The magic, version, and length
+//! always come first, whatever the version, so any version of this crate can find where a record ends.
+//!
+//! Records from a newer version of the format are kept in the file as they are, rather than being sent or
+//! dropped, so a file written by an upgraded program survives a downgrade. Lines holding a bare JSON object
+//! before the first record are events written before records were versioned, and are read as they are. A
+//! record whose checksum doesn't match, such as one cut short by the process dying while writing it, is
+//! skipped, up to the next record's header.
+
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::str;
+
+/// The first word of every record's header.
+pub const RECORD_MAGIC: &'static str = "sentry-rs-dead-letter";
+
+/// The version of the record format this crate writes.
+pub const FORMAT_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+/// A file failed events are appended to, one record each.
+pub struct DeadLetterFile {
+  /// Where failed events are appended.
+  pub path: PathBuf,
+  /// Once an append would grow the file past this many bytes, the file is moved aside to `<path>.1`
+  /// (replacing any earlier one) and a fresh file started.
+  pub max_bytes: u64,
+}
+
+/// A record read back from a dead letter file.
+enum Record {
+  /// The body of an event.
+  Event(String),
+  /// The whole of a record from a version of the format this crate doesn't know, to be written back.
+  Unknown(Vec<u8>),
+}
+
+impl DeadLetterFile {
+  /// Creates a dead letter file at `path`, rotated once it would grow past `max_bytes`.
+  pub fn new<P: Into<PathBuf>>(path: P, max_bytes: u64) -> DeadLetterFile {
+    DeadLetterFile {
+      path: path.into(),
+      max_bytes: max_bytes,
+    }
+  }
+
+  /// Where the file is moved to when it's rotated.
+  pub fn rotated_path(&self) -> PathBuf {
+    let mut path: OsString = self.path.clone().into_os_string();
+    path.push(".1");
+    PathBuf::from(path)
+  }
+
+  /// Appends a serialized event, rotating the file first if it would grow too large.
+  pub fn append(&self, body: &str) -> io::Result<()> {
+    let record = format!(
+      "{} {} {} {:08x}\n{}\n",
+      RECORD_MAGIC,
+      FORMAT_VERSION,
+      body.len(),
+      crc32(body.as_bytes()),
+      body
+    );
+    let len = fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0);
+    if len > 0 && len + record.len() as u64 > self.max_bytes {
+      fs::rename(&self.path, self.rotated_path())?;
+    }
+    let mut file = OpenOptions::new().create(true).read(true).append(true).open(&self.path)?;
+    // A record cut short by the process dying while writing it is left on a line of its own.
+    if !ends_with_newline(&mut file)? {
+      file.write_all(b"\n")?;
+    }
+    // Written at once, so records appended from different threads don't interleave.
+    file.write_all(record.as_bytes())
+  }
+
+  /// How many bytes of events are stored, counting the rotated file.
+  pub fn stored_bytes(&self) -> u64 {
+    [self.path.clone(), self.rotated_path()]
+      .iter()
+      .map(|path| fs::metadata(path).map(|m| m.len()).unwrap_or(0))
+      .sum()
+  }
+
+  /// Returns every stored event, oldest first, leaving them stored.
+  pub fn read(&self) -> io::Result<Vec<String>> {
+    let mut bodies = Vec::new();
+    for path in &[self.rotated_path(), self.path.clone()] {
+      for record in read_records(path)? {
+        if let Record::Event(body) = record {
+          bodies.push(body);
+        }
+      }
+    }
+    Ok(bodies)
+  }
+
+  /// Removes every stored event, returning them oldest first. Records from a newer version of the format
+  /// are left in the file.
+  pub fn take(&self) -> io::Result<Vec<String>> {
+    let mut bodies = Vec::new();
+    let mut unknown = Vec::new();
+    for path in &[self.rotated_path(), self.path.clone()] {
+      for record in read_records(path)? {
+        match record {
+          Record::Event(body) => bodies.push(body),
+          Record::Unknown(raw) => unknown.push(raw),
+        }
+      }
+      match fs::remove_file(path) {
+        Err(ref err) if err.kind() == ErrorKind::NotFound => {}
+        other => other?,
+      }
+    }
+    if !unknown.is_empty() {
+      let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+      file.write_all(&unknown.concat())?;
+    }
+    Ok(bodies)
+  }
+}
+
+/// Reads the records stored at `path`, none if there's no file.
+fn read_records(path: &Path) -> io::Result<Vec<Record>> {
+  let data = match fs::read(path) {
+    Ok(data) => data,
+    Err(ref err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+    Err(err) => return Err(err),
+  };
+
+  let mut records = Vec::new();
+  // Lines holding bare events only come before the first record, since records are only ever appended.
+  let mut versioned = false;
+  let mut start = 0;
+  while start < data.len() {
+    let line_end = data[start..].iter().position(|&b| b == b'\n').map_or(data.len(), |at| start + at);
+    let line = &data[start..line_end];
+    let after_line = line_end + 1;
+    if !versioned && line.first() == Some(&b'{') {
+      records.push(Record::Event(String::from_utf8_lossy(line).into_owned()));
+      start = after_line;
+      continue;
+    }
+    // Anything else that isn't a record's header is what's left of a record that couldn't be read, and
+    // skipped until the next header.
+    let (version, length, checksum) = match parse_header(line) {
+      Some(header) => header,
+      None => {
+        start = after_line;
+        continue;
+      }
+    };
+    versioned = true;
+
+    let body_end = after_line + length;
+    let record_end = if data.get(body_end) == Some(&b'\n') { body_end + 1 } else { body_end };
+    match data.get(after_line..body_end) {
+      Some(_) if version != FORMAT_VERSION => records.push(Record::Unknown(data[start..record_end].to_vec())),
+      Some(body) if checksum == Some(crc32(body)) && str::from_utf8(body).is_ok() => {
+        records.push(Record::Event(String::from_utf8_lossy(body).into_owned()))
+      }
+      _ => {
+        sdk_log!(Warn, "Skipping a corrupt or cut short dead letter record in {:?}", path);
+        start = after_line;
+        continue;
+      }
+    }
+    start = record_end;
+  }
+  Ok(records)
+}
+
+/// Whether `file` is empty or ends with a newline.
+fn ends_with_newline(file: &mut File) -> io::Result<bool> {
+  if file.seek(SeekFrom::End(0))? == 0 {
+    return Ok(true);
+  }
+  file.seek(SeekFrom::End(-1))?;
+  let mut last = [0; 1];
+  file.read_exact(&mut last)?;
+  Ok(last[0] == b'\n')
+}
+
+/// Reads a record's header as its version, body length, and checksum, when the version has one.
+fn parse_header(line: &[u8]) -> Option<(u32, usize, Option<u32>)> {
+  let mut fields = str::from_utf8(line).ok()?.split_whitespace();
+  if fields.next()? != RECORD_MAGIC {
+    return None;
+  }
+  let version = fields.next()?.parse().ok()?;
+  let length = fields.next()?.parse().ok()?;
+  let checksum = fields.next().and_then(|checksum| u32::from_str_radix(checksum, 16).ok());
+  Some((version, length, checksum))
+}
+
+/// The CRC-32 (IEEE) of `data`, as used by zip and PNG.
+fn crc32(data: &[u8]) -> u32 {
+  let mut crc = !0u32;
+  for &byte in data {
+    crc ^= u32::from(byte);
+    for _ in 0..8 {
+      crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+    }
+  }
+  !crc
+}
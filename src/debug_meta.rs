@@ -0,0 +1,153 @@
+//! Lists the images (the executable and the shared libraries) loaded into the process, for the
+//! `debug_meta` interface.
+//!
+//! Sentry matches the instruction addresses of stack frames against these images, so frames from a
+//! stripped release binary can be symbolicated on Sentry's side with the debug files uploaded for it.
+//! Images are found with `dl_iterate_phdr`, so they're only listed on Linux; elsewhere the list is empty.
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+/// An image loaded into the process. Each attribute is described in detail [HERE].
+///
+/// [HERE]: https://docs.sentry.io/clientdev/interfaces/debug/
+pub struct DebugImage {
+  /// The kind of image, such as `elf`.
+  #[serde(rename = "type")]
+  pub image_type: String,
+  /// The path the image was loaded from.
+  pub code_file: String,
+  /// The image's build id, in hex.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub code_id: Option<String>,
+  /// The id of the debug file that goes with the image, derived from its build id.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub debug_id: Option<String>,
+  /// The address the image was loaded at, in hex.
+  pub image_addr: String,
+  /// How many bytes of memory the image takes up.
+  pub image_size: u64,
+}
+
+/// Returns the images loaded into the process right now, starting with the executable.
+pub fn loaded_images() -> Vec<DebugImage> {
+  platform::loaded_images()
+}
+
+/// Turns an ELF build id into the debug id Sentry expects: its first 16 bytes as a uuid, with the first
+/// three fields byte-swapped the way they are on little endian machines.
+fn debug_id(build_id: &[u8]) -> Option<String> {
+  if build_id.len() < 16 {
+    return None;
+  }
+  let b = build_id;
+  Some(format!(
+    "{:02x}{:02x}{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}-{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
+    b[3], b[2], b[1], b[0], b[5], b[4], b[7], b[6], b[8], b[9], b[10], b[11], b[12], b[13], b[14], b[15]
+  ))
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+  use libc;
+
+  use super::{debug_id, DebugImage};
+
+  use std::env;
+  use std::ffi::CStr;
+  use std::mem;
+  use std::slice;
+
+  /// The note type holding a GNU build id.
+  const NT_GNU_BUILD_ID: u32 = 3;
+
+  pub fn loaded_images() -> Vec<DebugImage> {
+    let mut images: Vec<DebugImage> = Vec::new();
+    unsafe {
+      libc::dl_iterate_phdr(Some(visit), &mut images as *mut Vec<DebugImage> as *mut libc::c_void);
+    }
+    images
+  }
+
+  unsafe extern "C" fn visit(
+    info: *mut libc::dl_phdr_info,
+    _size: libc::size_t,
+    data: *mut libc::c_void,
+  ) -> libc::c_int {
+    let images = &mut *(data as *mut Vec<DebugImage>);
+    let info = &*info;
+    let name = if info.dlpi_name.is_null() {
+      String::new()
+    } else {
+      CStr::from_ptr(info.dlpi_name).to_string_lossy().into_owned()
+    };
+    // The executable is listed first, without a name.
+    let code_file = if name.is_empty() && images.is_empty() {
+      env::current_exe().map(|path| path.display().to_string()).unwrap_or_default()
+    } else {
+      name
+    };
+    if code_file.is_empty() || info.dlpi_phdr.is_null() {
+      return 0;
+    }
+
+    let headers = slice::from_raw_parts(info.dlpi_phdr, info.dlpi_phnum as usize);
+    let base = info.dlpi_addr as u64;
+    let mut start = u64::max_value();
+    let mut end = 0;
+    let mut build_id = None;
+    for header in headers {
+      if header.p_type == libc::PT_LOAD {
+        start = start.min(header.p_vaddr as u64);
+        end = end.max(header.p_vaddr as u64 + header.p_memsz as u64);
+      } else if header.p_type == libc::PT_NOTE && build_id.is_none() {
+        let notes = slice::from_raw_parts((base + header.p_vaddr as u64) as *const u8, header.p_memsz as usize);
+        build_id = find_build_id(notes);
+      }
+    }
+    if start >= end {
+      return 0;
+    }
+
+    images.push(DebugImage {
+      image_type: "elf".to_owned(),
+      code_file: code_file,
+      code_id: build_id.map(|id| id.iter().map(|byte| format!("{:02x}", byte)).collect()),
+      debug_id: build_id.and_then(debug_id),
+      image_addr: format!("{:#x}", base + start),
+      image_size: end - start,
+    });
+    0
+  }
+
+  /// Finds the GNU build id among the notes of a `PT_NOTE` segment.
+  fn find_build_id(mut notes: &[u8]) -> Option<&[u8]> {
+    let header = 3 * mem::size_of::<u32>();
+    let align = |n: usize| (n + 3) & !3;
+    while notes.len() >= header {
+      let word = |at: usize| {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(&notes[at..at + 4]);
+        u32::from_ne_bytes(bytes)
+      };
+      let (name_size, desc_size, note_type) = (word(0) as usize, word(4) as usize, word(8));
+      let desc_start = header + align(name_size);
+      let desc_end = desc_start + desc_size;
+      if desc_end > notes.len() {
+        return None;
+      }
+      if note_type == NT_GNU_BUILD_ID && &notes[header..header + name_size] == b"GNU\0" {
+        return Some(&notes[desc_start..desc_end]);
+      }
+      notes = &notes[(header + align(name_size) + align(desc_size)).min(notes.len())..];
+    }
+    None
+  }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod platform {
+  use super::DebugImage;
+
+  pub fn loaded_images() -> Vec<DebugImage> {
+    Vec::new()
+  }
+}
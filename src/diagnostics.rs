@@ -0,0 +1,113 @@
+//! Decides where the SDK's own log messages go, such as why an event failed to send.
+//!
+//! By default they go through the `log` crate like any other library's, under `sentry_rs` targets. A sink
+//! set with `set_sink` receives them instead, so the SDK can be debugged without touching how the
+//! application logs. Without a sink, setting the `SENTRY_RS_DEBUG` environment variable to a level
+//! (`error`, `warn`, `info`, `debug`, or `trace`) prints messages at that level or above to stderr
+//! instead.
+//!
+//! Work the SDK does for itself, such as sending events from its own threads, is marked with `enter`.
+//! `SentryLogger` never turns records logged during that work into events, even ones from other crates
+//! like `hyper`, so reporting to Sentry can't feed back into itself.
+
+use log::{Level, LevelFilter};
+
+use std::cell::Cell;
+use std::env;
+use std::fmt;
+use std::sync::RwLock;
+
+/// Receives the SDK's own log messages, with their level.
+pub type Sink = Box<Fn(Level, &str) + Send + Sync>;
+
+lazy_static! {
+  static ref SINK: RwLock<Option<Sink>> = RwLock::new(None);
+  static ref ENV_LEVEL: Option<LevelFilter> = env::var("SENTRY_RS_DEBUG").ok().and_then(|level| level.parse().ok());
+}
+
+thread_local! {
+  static INTERNAL: Cell<bool> = Cell::new(false);
+}
+
+/// Marks the current thread as doing the SDK's own work until dropped.
+pub struct InternalGuard {
+  previous: bool,
+}
+
+impl Drop for InternalGuard {
+  fn drop(&mut self) {
+    let previous = self.previous;
+    INTERNAL.with(|internal| internal.set(previous));
+  }
+}
+
+/// Marks the current thread as doing the SDK's own work until the returned guard is dropped.
+pub fn enter() -> InternalGuard {
+  InternalGuard {
+    previous: INTERNAL.with(|internal| internal.replace(true)),
+  }
+}
+
+/// Whether the current thread is doing the SDK's own work, so anything it logs shouldn't reach Sentry.
+pub fn is_internal() -> bool {
+  INTERNAL.with(|internal| internal.get())
+}
+
+/// Sends the SDK's own log messages to `sink` instead of the `log` crate, replacing any sink set before.
+///
+/// # Examples
+///
+/// ```rust
+/// extern crate log;
+/// extern crate sentry_rs;
+///
+/// use log::Level;
+/// use sentry_rs::diagnostics;
+///
+/// fn main() {
+///   diagnostics::set_sink(|level: Level, message: &str| {
+///     if level <= Level::Info {
+///       eprintln!("[sentry-rs] {}: {}", level, message);
+///     }
+///   });
+/// }
+/// ```
+pub fn set_sink<F>(sink: F)
+where
+  F: Fn(Level, &str) + 'static + Send + Sync,
+{
+  let mut current = match SINK.write() {
+    Ok(guard) => guard,
+    Err(poisoned) => poisoned.into_inner(),
+  };
+  *current = Some(Box::new(sink));
+}
+
+/// Sends the SDK's own log messages back to the `log` crate.
+pub fn clear_sink() {
+  let mut current = match SINK.write() {
+    Ok(guard) => guard,
+    Err(poisoned) => poisoned.into_inner(),
+  };
+  *current = None;
+}
+
+/// Logs one of the SDK's own messages from `target`, for the crate's internal `sdk_log!` macro. Hidden
+/// from the docs, since only the SDK's own messages belong here.
+#[doc(hidden)]
+pub fn log(level: Level, target: &str, args: fmt::Arguments) {
+  let sink = match SINK.read() {
+    Ok(guard) => guard,
+    Err(poisoned) => poisoned.into_inner(),
+  };
+  if let Some(ref sink) = *sink {
+    sink(level, &fmt::format(args));
+  } else if let Some(env_level) = *ENV_LEVEL {
+    if level <= env_level {
+      eprintln!("[{} {}] {}", level, target, args);
+    }
+  } else {
+    let _internal = enter();
+    log!(target: target, level, "{}", args);
+  }
+}
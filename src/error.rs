@@ -0,0 +1,354 @@
+//! Errors that can happen while getting an event to Sentry.
+//!
+//! Each operation returns the error that describes how it can fail, such as `CaptureError` for capturing
+//! an event. All of them convert into `Error`, so downstream code can use `?` to turn any SDK failure into
+//! its own error type through a single `From<sentry_rs::Error>`.
+
+use hyper::StatusCode;
+
+use config::ConfigError;
+use models::CredentialsParseError;
+use request::{DispatchErrorKind, HttpDispatchError, TlsError};
+
+use std::error::Error as StdError;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+/// Any error from this crate.
+pub enum Error {
+  /// A DSN couldn't be parsed into credentials.
+  Credentials(CredentialsParseError),
+  /// The TLS client requests are sent with couldn't be set up.
+  Tls(TlsError),
+  /// An event couldn't be sent to Sentry, such as when its host couldn't be resolved or connected to.
+  Transport(HttpDispatchError),
+  /// Sentry didn't respond in time.
+  Timeout(HttpDispatchError),
+  /// Sentry responded, but refused the event.
+  Rejected(Rejection),
+  /// Sentry redirected the event somewhere it wasn't followed.
+  Redirected(Redirect),
+  /// An event couldn't be serialized.
+  Serialization(String),
+  /// An event never reached the worker that sends it, or was dropped before it was sent. This never holds
+  /// `CaptureError::Send`, whose error becomes one of the other variants.
+  Worker(CaptureError),
+  /// A configuration couldn't be read or used.
+  Config(ConfigError),
+}
+
+#[derive(Debug, PartialEq)]
+/// Why an event never made it to Sentry.
+pub enum CaptureError {
+  /// The event was dropped before it was sent, such as when the worker has gone away.
+  Canceled,
+  /// Too many events were already waiting to be sent, so the event was dropped. Retrying later may
+  /// succeed, or the event can be kept somewhere else.
+  QueueFull,
+  /// The client was closed, so it no longer takes events.
+  Closed,
+  /// The worker's thread couldn't be spawned to send the event, with the system's reason.
+  Spawn(String),
+  /// Sending the event to Sentry failed.
+  Send(SendError),
+}
+
+#[derive(Debug, PartialEq)]
+/// Why sending an event to Sentry failed.
+pub enum SendError {
+  /// Sentry's host couldn't be resolved.
+  Dns(HttpDispatchError),
+  /// A secure connection to Sentry couldn't be established.
+  Tls(HttpDispatchError),
+  /// Sentry didn't respond in time.
+  Timeout(HttpDispatchError),
+  /// The connection to Sentry failed, or the request couldn't be sent for some other reason.
+  Dispatch(HttpDispatchError),
+  /// Sentry responded, but refused the event.
+  Status(Rejection),
+  /// Sentry redirected the event to another host or port, from `https` to `http`, with a redirect that
+  /// doesn't keep the request as it is, or more than `MAX_REDIRECTS` times. Redirects to the same host and
+  /// port, or from `http` to `https`, are followed.
+  Redirected(Redirect),
+  /// The event couldn't be serialized.
+  Serialization(String),
+}
+
+#[derive(Debug, PartialEq)]
+/// Why `Sentry::verify` couldn't deliver its test event.
+pub enum VerifyError {
+  /// Sentry refused the credentials, or doesn't know their project.
+  BadCredentials(Rejection),
+  /// Sentry's host couldn't be resolved, connected to, or didn't respond in time.
+  Unreachable(SendError),
+  /// A secure connection to Sentry couldn't be established.
+  Tls(SendError),
+  /// Sentry was reached, but the test event failed for some other reason.
+  Rejected(SendError),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Sentry's response when it refuses an event.
+pub struct Rejection {
+  /// The status code Sentry responded with.
+  pub status: StatusCode,
+  /// Sentry's explanation from the `X-Sentry-Error` header, if it sent one.
+  pub reason: Option<String>,
+  /// The body of the response.
+  pub body: String,
+}
+
+impl Rejection {
+  /// Whether Sentry might accept the event if it's sent again: it was rate limited, timed out reading the
+  /// request, or failed on its side with a `5xx`. Anything else, such as `400 Bad Request`,
+  /// `401 Unauthorized`, or `413 Payload Too Large`, would be refused again.
+  pub fn is_retryable(&self) -> bool {
+    match self.status {
+      StatusCode::TooManyRequests | StatusCode::RequestTimeout => true,
+      status => status.is_server_error(),
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A redirect from Sentry that wasn't followed.
+pub struct Redirect {
+  /// The status code Sentry responded with.
+  pub status: StatusCode,
+  /// Where Sentry redirected to, from the `Location` header, if it sent one.
+  pub location: Option<String>,
+}
+
+impl fmt::Display for Redirect {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self.location {
+      Some(ref location) => write!(f, "{} to {}", self.status, location),
+      None => write!(f, "{} without a location", self.status),
+    }
+  }
+}
+
+impl StdError for CaptureError {
+  fn description(&self) -> &str {
+    match *self {
+      CaptureError::Canceled => "event was dropped before it was sent",
+      CaptureError::QueueFull => "too many events are waiting to be sent",
+      CaptureError::Closed => "the client was closed",
+      CaptureError::Spawn(_) => "the worker thread couldn't be spawned",
+      CaptureError::Send(ref err) => err.description(),
+    }
+  }
+}
+
+impl fmt::Display for CaptureError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      CaptureError::Canceled => write!(f, "Event was dropped before it was sent"),
+      CaptureError::QueueFull => write!(f, "Too many events are waiting to be sent"),
+      CaptureError::Closed => write!(f, "The client was closed"),
+      CaptureError::Spawn(ref reason) => write!(f, "The worker thread couldn't be spawned: {}", reason),
+      CaptureError::Send(ref err) => write!(f, "{}", err),
+    }
+  }
+}
+
+impl From<SendError> for CaptureError {
+  fn from(err: SendError) -> CaptureError {
+    CaptureError::Send(err)
+  }
+}
+
+impl SendError {
+  /// Whether sending the event again might succeed, such as after a timeout, a dropped connection, or a
+  /// `429` or `5xx` from Sentry. Events Sentry would always refuse, or that can never be sent, such as
+  /// with a TLS certificate that isn't trusted, aren't kept in the dead letter file.
+  pub fn is_retryable(&self) -> bool {
+    match *self {
+      SendError::Dns(ref err) | SendError::Timeout(ref err) | SendError::Dispatch(ref err) => err.is_retryable(),
+      SendError::Tls(_) | SendError::Redirected(_) | SendError::Serialization(_) => false,
+      SendError::Status(ref rejection) => rejection.is_retryable(),
+    }
+  }
+}
+
+impl StdError for SendError {
+  fn description(&self) -> &str {
+    match *self {
+      SendError::Dns(_) => "sentry's host couldn't be resolved",
+      SendError::Tls(_) => "a secure connection to sentry couldn't be established",
+      SendError::Timeout(_) => "sentry didn't respond in time",
+      SendError::Dispatch(ref err) => err.description(),
+      SendError::Status(_) => "sentry rejected the event",
+      SendError::Redirected(_) => "sentry redirected the event",
+      SendError::Serialization(_) => "the event couldn't be serialized",
+    }
+  }
+}
+
+impl fmt::Display for SendError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      SendError::Dns(ref err) => write!(f, "Failed to resolve Sentry's host: {}", err),
+      SendError::Tls(ref err) => write!(f, "Failed to connect securely to Sentry: {}", err),
+      SendError::Timeout(ref err) => write!(f, "Timed out sending event: {}", err),
+      SendError::Dispatch(ref err) => write!(f, "Failed to send event: {}", err),
+      SendError::Status(ref rejection) => match rejection.reason {
+        Some(ref reason) => write!(f, "Sentry rejected the event: {} ({})", rejection.status, reason),
+        None => write!(f, "Sentry rejected the event: {}", rejection.status),
+      },
+      SendError::Redirected(ref redirect) => write!(f, "Sentry redirected the event: {}", redirect),
+      SendError::Serialization(ref message) => write!(f, "Failed to serialize event: {}", message),
+    }
+  }
+}
+
+impl From<HttpDispatchError> for SendError {
+  fn from(err: HttpDispatchError) -> SendError {
+    match err.kind() {
+      DispatchErrorKind::Dns => SendError::Dns(err),
+      DispatchErrorKind::Tls => SendError::Tls(err),
+      DispatchErrorKind::Timeout => SendError::Timeout(err),
+      DispatchErrorKind::Connection | DispatchErrorKind::Other => SendError::Dispatch(err),
+    }
+  }
+}
+
+impl StdError for VerifyError {
+  fn description(&self) -> &str {
+    match *self {
+      VerifyError::BadCredentials(_) => "sentry refused the credentials",
+      VerifyError::Unreachable(ref err) | VerifyError::Tls(ref err) | VerifyError::Rejected(ref err) => {
+        err.description()
+      }
+    }
+  }
+}
+
+impl fmt::Display for VerifyError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      VerifyError::BadCredentials(ref rejection) => match rejection.reason {
+        Some(ref reason) => write!(f, "Sentry refused the credentials: {} ({})", rejection.status, reason),
+        None => write!(f, "Sentry refused the credentials: {}", rejection.status),
+      },
+      VerifyError::Unreachable(ref err) | VerifyError::Tls(ref err) | VerifyError::Rejected(ref err) => {
+        write!(f, "{}", err)
+      }
+    }
+  }
+}
+
+impl From<SendError> for VerifyError {
+  fn from(err: SendError) -> VerifyError {
+    match err {
+      SendError::Status(rejection) => match rejection.status {
+        StatusCode::Unauthorized | StatusCode::Forbidden | StatusCode::NotFound => {
+          VerifyError::BadCredentials(rejection)
+        }
+        _ => VerifyError::Rejected(SendError::Status(rejection)),
+      },
+      SendError::Tls(_) => VerifyError::Tls(err),
+      SendError::Dns(_) | SendError::Timeout(_) | SendError::Dispatch(_) => VerifyError::Unreachable(err),
+      SendError::Redirected(_) | SendError::Serialization(_) => VerifyError::Rejected(err),
+    }
+  }
+}
+
+impl StdError for Error {
+  fn description(&self) -> &str {
+    match *self {
+      Error::Credentials(_) => "the credentials couldn't be parsed",
+      Error::Tls(ref err) => err.description(),
+      Error::Transport(ref err) | Error::Timeout(ref err) => err.description(),
+      Error::Rejected(_) => "sentry rejected the event",
+      Error::Redirected(_) => "sentry redirected the event",
+      Error::Serialization(_) => "the event couldn't be serialized",
+      Error::Worker(ref err) => err.description(),
+      Error::Config(ref err) => err.description(),
+    }
+  }
+
+  fn source(&self) -> Option<&(StdError + 'static)> {
+    match *self {
+      Error::Credentials(ref err) => Some(err),
+      Error::Tls(ref err) => Some(err),
+      Error::Transport(ref err) | Error::Timeout(ref err) => Some(err),
+      Error::Rejected(_) | Error::Redirected(_) | Error::Serialization(_) => None,
+      Error::Worker(ref err) => Some(err),
+      Error::Config(ref err) => Some(err),
+    }
+  }
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      Error::Credentials(ref err) => write!(f, "Invalid credentials: {}", err),
+      Error::Tls(ref err) => write!(f, "{}", err),
+      Error::Transport(ref err) => write!(f, "Failed to send event: {}", err),
+      Error::Timeout(ref err) => write!(f, "Timed out sending event: {}", err),
+      Error::Rejected(ref rejection) => match rejection.reason {
+        Some(ref reason) => write!(f, "Sentry rejected the event: {} ({})", rejection.status, reason),
+        None => write!(f, "Sentry rejected the event: {}", rejection.status),
+      },
+      Error::Redirected(ref redirect) => write!(f, "Sentry redirected the event: {}", redirect),
+      Error::Serialization(ref message) => write!(f, "Failed to serialize event: {}", message),
+      Error::Worker(ref err) => write!(f, "{}", err),
+      Error::Config(ref err) => write!(f, "{}", err),
+    }
+  }
+}
+
+impl From<CredentialsParseError> for Error {
+  fn from(err: CredentialsParseError) -> Error {
+    Error::Credentials(err)
+  }
+}
+
+impl From<TlsError> for Error {
+  fn from(err: TlsError) -> Error {
+    Error::Tls(err)
+  }
+}
+
+impl From<HttpDispatchError> for Error {
+  fn from(err: HttpDispatchError) -> Error {
+    Error::from(SendError::from(err))
+  }
+}
+
+impl From<SendError> for Error {
+  fn from(err: SendError) -> Error {
+    match err {
+      SendError::Dns(err) | SendError::Tls(err) | SendError::Dispatch(err) => Error::Transport(err),
+      SendError::Timeout(err) => Error::Timeout(err),
+      SendError::Status(rejection) => Error::Rejected(rejection),
+      SendError::Redirected(redirect) => Error::Redirected(redirect),
+      SendError::Serialization(message) => Error::Serialization(message),
+    }
+  }
+}
+
+impl From<CaptureError> for Error {
+  fn from(err: CaptureError) -> Error {
+    match err {
+      CaptureError::Send(err) => Error::from(err),
+      other => Error::Worker(other),
+    }
+  }
+}
+
+impl From<ConfigError> for Error {
+  fn from(err: ConfigError) -> Error {
+    Error::Config(err)
+  }
+}
+
+impl From<VerifyError> for Error {
+  fn from(err: VerifyError) -> Error {
+    match err {
+      VerifyError::BadCredentials(rejection) => Error::Rejected(rejection),
+      VerifyError::Unreachable(err) | VerifyError::Tls(err) | VerifyError::Rejected(err) => Error::from(err),
+    }
+  }
+}
@@ -0,0 +1,511 @@
+//! Filters applied to every event just before it's sent, whichever integration it came from, and patterns
+//! for dropping known-noisy events before they're queued.
+
+use models::{Event, Fingerprint, FingerprintComponent, Level, StackFrame};
+use regex::{self, Regex};
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+/// Drops and renames tags before an event is sent.
+///
+/// # Examples
+///
+/// ```rust
+/// use sentry_rs::filter::TagFilter;
+/// use std::collections::HashMap;
+///
+/// let mut filter = TagFilter::new();
+/// filter.deny.push("auth_token".to_owned());
+/// filter.rename.insert("usr".to_owned(), "user".to_owned());
+///
+/// let mut tags = HashMap::new();
+/// tags.insert("auth_token".to_owned(), "hunter2".to_owned());
+/// tags.insert("usr".to_owned(), "bob".to_owned());
+/// filter.apply(&mut tags);
+/// assert_eq!(tags.get("user"), Some(&"bob".to_owned()));
+/// assert!(!tags.contains_key("auth_token"));
+/// ```
+pub struct TagFilter {
+  /// If not empty, only tags with these keys are kept.
+  pub allow: Vec<String>,
+  /// Tags with these keys are dropped.
+  pub deny: Vec<String>,
+  /// Tags are renamed from the key to the value, after `allow` and `deny` have been checked against
+  /// their original keys.
+  pub rename: HashMap<String, String>,
+}
+
+impl TagFilter {
+  /// Creates a filter that keeps every tag as-is.
+  pub fn new() -> TagFilter {
+    TagFilter::default()
+  }
+
+  /// Whether a tag with `key` is kept.
+  pub fn is_allowed(&self, key: &str) -> bool {
+    if self.deny.iter().any(|denied| denied == key) {
+      return false;
+    }
+    self.allow.is_empty() || self.allow.iter().any(|allowed| allowed == key)
+  }
+
+  /// Drops and renames `tags` in place.
+  pub fn apply(&self, tags: &mut HashMap<String, String>) {
+    let filtered = tags
+      .drain()
+      .filter(|&(ref key, _)| self.is_allowed(key))
+      .map(|(key, value)| match self.rename.get(&key) {
+        Some(renamed) => (renamed.clone(), value),
+        None => (key, value),
+      })
+      .collect::<Vec<(String, String)>>();
+    tags.extend(filtered);
+  }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+/// Trims the frames of a stack trace that only show how it was taken, or how the thread was started, so
+/// Sentry shows (and groups by) the frames in between.
+///
+/// Frames are listed from where the stack trace was taken down to where the thread started. Frames are
+/// trimmed from each end while their function matches one of the patterns for that end, stopping at the
+/// first that doesn't, so the same function in the middle of the stack is kept. A pattern ending in `::`
+/// (or `<`, opening a type's generic parameters) is a prefix matching every function under it, and any
+/// other pattern only matches that exact function, so `main` doesn't match `main_service::run`. A
+/// stack trace is never trimmed to nothing.
+///
+/// # Examples
+///
+/// ```rust
+/// use sentry_rs::filter::FrameTrim;
+///
+/// let mut trim = FrameTrim::new();
+/// trim.top.push("my_app::errors::".to_owned());
+/// ```
+pub struct FrameTrim {
+  /// Patterns of the frames trimmed from where the stack trace was taken, such as the panic hook.
+  pub top: Vec<String>,
+  /// Patterns of the frames trimmed from where the thread started, such as the runtime calling `main`.
+  pub bottom: Vec<String>,
+}
+
+lazy_static! {
+  /// The disambiguator following a crate name in a function name demangled from the v0 scheme.
+  static ref DISAMBIGUATOR: Regex = Regex::new(r"\[[0-9a-f]+\]").unwrap();
+  /// The hash ending a function name demangled from the legacy scheme.
+  static ref LEGACY_HASH: Regex = Regex::new(r"::h[0-9a-f]{16}$").unwrap();
+}
+
+/// The frames of the standard library and this crate that lead from a panic to its stack trace.
+const PANIC_FRAMES: &'static [&'static str] = &[
+  "backtrace::",
+  "sentry_rs::",
+  "std::panicking::",
+  "std::panic::",
+  "std::sys_common::backtrace::__rust_end_short_backtrace",
+  "std::sys::backtrace::__rust_end_short_backtrace",
+  "core::panicking::",
+  "rust_begin_unwind",
+  "__rustc::rust_begin_unwind",
+  "<alloc::boxed::Box<",
+];
+
+/// The frames of the standard library and the system that start a process or thread and call into it.
+const RUNTIME_FRAMES: &'static [&'static str] = &[
+  "std::rt::",
+  "std::panicking::",
+  "std::panic::",
+  "std::thread::",
+  "std::sys::",
+  "std::sys_common::",
+  "core::ops::function::",
+  "<alloc::boxed::Box<",
+  "main",
+  "__libc_start_main",
+  "__libc_start_call_main",
+  "_start",
+  "start_thread",
+  "clone",
+  "__clone",
+  "__scrt_common_main",
+  "__scrt_common_main_seh",
+  "BaseThreadInitThunk",
+  "RtlUserThreadStart",
+];
+
+impl FrameTrim {
+  /// Trims the frames of the panic machinery of the standard library and this crate from the top, and
+  /// the frames of the runtime and system that start processes and threads from the bottom.
+  pub fn new() -> FrameTrim {
+    FrameTrim {
+      top: PANIC_FRAMES.iter().map(|prefix| prefix.to_string()).collect(),
+      bottom: RUNTIME_FRAMES.iter().map(|prefix| prefix.to_string()).collect(),
+    }
+  }
+
+  /// Trims `frames` in place. Crate disambiguators in function names, like the `[1a2b3c4d]` in
+  /// `std[1a2b3c4d]::rt::lang_start`, and the hashes ending them, like `::h0123456789abcdef`, are ignored.
+  pub fn apply(&self, frames: &mut Vec<StackFrame>) {
+    let matches = |patterns: &Vec<String>, frame: &StackFrame| {
+      let function = DISAMBIGUATOR.replace_all(&frame.function, "");
+      let function = LEGACY_HASH.replace(&function, "");
+      patterns.iter().any(|pattern| {
+        if pattern.ends_with("::") || pattern.ends_with('<') {
+          function.starts_with(pattern.as_str())
+        } else {
+          function == pattern.as_str()
+        }
+      })
+    };
+    let start = frames
+      .iter()
+      .position(|frame| !matches(&self.top, frame))
+      .unwrap_or(frames.len());
+    let end = frames
+      .iter()
+      .rposition(|frame| !matches(&self.bottom, frame))
+      .map_or(0, |last| last + 1);
+    if start < end {
+      frames.truncate(end);
+      frames.drain(..start);
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields)]
+/// Caps how many frames of a stack trace are sent, keeping the `top` frames nearest where it was taken and
+/// the `bottom` frames nearest where the thread started, and dropping those in between. Deep recursion can
+/// otherwise leave a stack trace with hundreds of frames, and an event too large for Sentry to accept.
+///
+/// # Examples
+///
+/// ```rust
+/// use sentry_rs::filter::FrameLimit;
+/// use sentry_rs::stacktrace::{self, StacktraceOptions};
+///
+/// let mut frames = stacktrace::capture_stacktrace(0, &StacktraceOptions::default());
+/// FrameLimit::new(2, 1).apply(&mut frames);
+/// assert!(frames.len() <= 3);
+/// ```
+pub struct FrameLimit {
+  /// How many frames are kept from where the stack trace was taken.
+  pub top: usize,
+  /// How many frames are kept from where the thread started.
+  pub bottom: usize,
+}
+
+impl FrameLimit {
+  /// Keeps at most `top` frames from where the stack trace was taken, and `bottom` from where the thread
+  /// started.
+  pub fn new(top: usize, bottom: usize) -> FrameLimit {
+    FrameLimit {
+      top: top,
+      bottom: bottom,
+    }
+  }
+
+  /// Drops the frames in between the `top` and `bottom` ones, if there are any.
+  pub fn apply(&self, frames: &mut Vec<StackFrame>) {
+    if frames.len() > self.top + self.bottom {
+      let end = frames.len() - self.bottom;
+      frames.drain(self.top..end);
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// Rewrites the paths of the files in stack frames before they're sent, so paths on the machine that
+/// built the program don't leak to Sentry, and frames from the same crate version group together however
+/// it was built. The first rule matching a path rewrites it, and paths no rule matches are sent as they
+/// are.
+///
+/// # Examples
+///
+/// ```rust
+/// use sentry_rs::filter::PathRewrite;
+///
+/// let mut rewrite = PathRewrite::new();
+/// rewrite.add_prefix("/home/builder/my_app/", "");
+/// assert_eq!(rewrite.rewrite("/home/builder/my_app/src/main.rs"), "src/main.rs");
+/// assert_eq!(
+///   rewrite.rewrite("/home/builder/.cargo/registry/src/github.com-1ecc6299db9ec823/serde-1.0.80/src/de.rs"),
+///   "serde-1.0.80/src/de.rs"
+/// );
+/// ```
+pub struct PathRewrite {
+  /// The rules, in the order they're tried.
+  pub rules: Vec<PathRule>,
+}
+
+#[derive(Clone, Debug)]
+/// Rewrites the part of a path matching a regex with a replacement, which can refer to the regex's groups
+/// like `$1`.
+pub struct PathRule {
+  /// What's rewritten.
+  pub pattern: Regex,
+  /// What it's rewritten to.
+  pub replacement: String,
+}
+
+/// The places Cargo and rustc keep sources, leaving the crate name and version or the standard library
+/// path behind.
+const BUILD_PATHS: &'static [&'static str] = &[
+  r"^.*[/\\]registry[/\\]src[/\\][^/\\]+[/\\]",
+  r"^.*[/\\]git[/\\]checkouts[/\\]",
+  r"^/rustc/[0-9a-f]+/",
+];
+
+impl PathRewrite {
+  /// Rewrites the paths of crates from Cargo's registry and git checkouts to start at the crate, like
+  /// `serde-1.0.80/src/de.rs`, and the paths of the standard library to start at `library/`.
+  pub fn new() -> PathRewrite {
+    PathRewrite {
+      rules: BUILD_PATHS
+        .iter()
+        .map(|pattern| PathRule::new(pattern, "").unwrap())
+        .collect(),
+    }
+  }
+
+  /// Rewrites paths starting with `prefix`, such as the directory the program was built in, to start with
+  /// `replacement` instead.
+  pub fn add_prefix(&mut self, prefix: &str, replacement: &str) {
+    self.rules.push(PathRule::prefix(prefix, replacement));
+  }
+
+  /// `path` rewritten by the first rule that matches it.
+  pub fn rewrite<'a>(&self, path: &'a str) -> Cow<'a, str> {
+    match self.rules.iter().find(|rule| rule.pattern.is_match(path)) {
+      Some(rule) => rule.pattern.replace(path, rule.replacement.as_str()),
+      None => Cow::Borrowed(path),
+    }
+  }
+
+  /// Rewrites the file name and absolute path of each of `frames` in place.
+  pub fn apply(&self, frames: &mut Vec<StackFrame>) {
+    for frame in frames.iter_mut() {
+      if let Cow::Owned(filename) = self.rewrite(&frame.filename) {
+        frame.filename = filename;
+      }
+      if let Some(abs_path) = frame.abs_path.take() {
+        frame.abs_path = Some(self.rewrite(&abs_path).into_owned());
+      }
+    }
+  }
+}
+
+impl PathRule {
+  /// A rule rewriting what `pattern` matches with `replacement`. Fails if `pattern` isn't a valid regex.
+  pub fn new(pattern: &str, replacement: &str) -> Result<PathRule, regex::Error> {
+    Ok(PathRule {
+      pattern: Regex::new(pattern)?,
+      replacement: replacement.to_owned(),
+    })
+  }
+
+  /// A rule rewriting `prefix` at the start of a path with `replacement`.
+  pub fn prefix(prefix: &str, replacement: &str) -> PathRule {
+    PathRule {
+      pattern: Regex::new(&format!("^{}", regex::escape(prefix))).unwrap(),
+      replacement: replacement.replace('$', "$$"),
+    }
+  }
+}
+
+impl PartialEq for PathRule {
+  /// Rules are equal when they were made from the same pattern and replacement.
+  fn eq(&self, other: &PathRule) -> bool {
+    self.pattern.as_str() == other.pattern.as_str() && self.replacement == other.replacement
+  }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+/// How events are grouped when they're sent without a fingerprint of their own.
+///
+/// # Examples
+///
+/// ```rust
+/// use sentry_rs::filter::FingerprintStrategy;
+/// use sentry_rs::models::Event;
+///
+/// let event = Event::new("db", "error", "Query failed", Some("db::query"), None, None, None, None, None, None);
+/// let fingerprint = FingerprintStrategy::CulpritAndLevel.fingerprint(&event);
+/// assert_eq!(fingerprint.to_strings(), vec!["db::query".to_owned(), "error".to_owned()]);
+/// ```
+pub enum FingerprintStrategy {
+  /// Leaves grouping up to Sentry.
+  Default,
+  /// Groups by the template the message was logged from, so the same message with different arguments is
+  /// one issue. Events logged without a template are grouped by their message.
+  MessageTemplate,
+  /// Groups by culprit, or transaction when there's no culprit, and level.
+  CulpritAndLevel,
+  /// Groups by the module and function of the top in-app frame of the stack trace. Events without one are
+  /// left up to Sentry.
+  InAppFrame,
+}
+
+impl Default for FingerprintStrategy {
+  fn default() -> FingerprintStrategy {
+    FingerprintStrategy::Default
+  }
+}
+
+impl FingerprintStrategy {
+  /// The fingerprint this strategy gives `event`, ignoring any it already has.
+  pub fn fingerprint(&self, event: &Event) -> Fingerprint {
+    let components = match *self {
+      FingerprintStrategy::Default => Vec::new(),
+      FingerprintStrategy::MessageTemplate => match event.logentry {
+        Some(ref entry) => vec![FingerprintComponent::Custom(entry.message.clone())],
+        None => vec![FingerprintComponent::Message],
+      },
+      FingerprintStrategy::CulpritAndLevel => {
+        let culprit = match event.culprit {
+          Some(ref culprit) => FingerprintComponent::Custom(culprit.clone()),
+          None if event.transaction.is_some() => FingerprintComponent::Transaction,
+          None => FingerprintComponent::Default,
+        };
+        vec![culprit, FingerprintComponent::Custom(event.level.to_string())]
+      }
+      FingerprintStrategy::InAppFrame => {
+        let frames = event.stacktrace.as_ref().map_or(&[][..], |frames| &frames[..]);
+        match frames.iter().find(|frame| frame.in_app) {
+          Some(frame) => vec![
+            FingerprintComponent::Custom(frame.module.clone().unwrap_or_else(|| frame.filename.clone())),
+            FingerprintComponent::Custom(DISAMBIGUATOR.replace_all(&frame.function, "").into_owned()),
+          ],
+          None => Vec::new(),
+        }
+      }
+    };
+    Fingerprint::new(components)
+  }
+
+  /// Gives `event` this strategy's fingerprint, unless it already has one.
+  pub fn apply(&self, event: &mut Event) {
+    if event.fingerprint.is_empty() {
+      event.fingerprint = self.fingerprint(event);
+    }
+  }
+}
+
+#[derive(Clone, Debug)]
+/// Matches the message, logger, or culprit of events to ignore, set on `SentryOptions::ignore_errors`.
+///
+/// # Examples
+///
+/// ```rust
+/// use sentry_rs::filter::ErrorPattern;
+///
+/// let disconnects = ErrorPattern::regex("^Connection reset( by peer)?$").unwrap();
+/// assert!(disconnects.matches("Connection reset by peer"));
+///
+/// let timeouts: ErrorPattern = "Upstream timed out".into();
+/// assert!(!timeouts.matches("Upstream timed out twice"));
+/// ```
+pub enum ErrorPattern {
+  /// Matches text equal to this.
+  Exact(String),
+  /// Matches text this regex finds a match in. Anchor it with `^` and `$` to match the whole text.
+  Regex(Regex),
+}
+
+impl ErrorPattern {
+  /// A pattern matching text `pattern` finds a match in. Fails if `pattern` isn't a valid regex.
+  pub fn regex(pattern: &str) -> Result<ErrorPattern, regex::Error> {
+    Regex::new(pattern).map(ErrorPattern::Regex)
+  }
+
+  /// Whether `text` matches this pattern.
+  pub fn matches(&self, text: &str) -> bool {
+    match *self {
+      ErrorPattern::Exact(ref exact) => exact == text,
+      ErrorPattern::Regex(ref regex) => regex.is_match(text),
+    }
+  }
+
+  /// Whether the message, logger, or culprit of `event` matches this pattern.
+  pub fn matches_event(&self, event: &Event) -> bool {
+    self.matches(&event.message) || self.matches(&event.logger)
+      || event.culprit.as_ref().map_or(false, |culprit| self.matches(culprit))
+  }
+}
+
+impl PartialEq for ErrorPattern {
+  /// Regexes are equal when they were made from the same pattern.
+  fn eq(&self, other: &ErrorPattern) -> bool {
+    match (self, other) {
+      (&ErrorPattern::Exact(ref a), &ErrorPattern::Exact(ref b)) => a == b,
+      (&ErrorPattern::Regex(ref a), &ErrorPattern::Regex(ref b)) => a.as_str() == b.as_str(),
+      _ => false,
+    }
+  }
+}
+
+impl<'a> From<&'a str> for ErrorPattern {
+  fn from(exact: &'a str) -> ErrorPattern {
+    ErrorPattern::Exact(exact.to_owned())
+  }
+}
+
+impl From<String> for ErrorPattern {
+  fn from(exact: String) -> ErrorPattern {
+    ErrorPattern::Exact(exact)
+  }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+/// The least severe level sent from particular loggers, overriding `Sentry::set_min_level` for them.
+///
+/// A pattern ending in `*` matches every logger starting with the rest of it, and any other pattern only
+/// the logger with that name. When several patterns match a logger, the longest decides its level.
+///
+/// # Examples
+///
+/// ```rust
+/// use sentry_rs::filter::LoggerLevels;
+/// use sentry_rs::models::Level;
+///
+/// let mut levels = LoggerLevels::new();
+/// levels.levels.insert("db::*".to_owned(), Level::Error);
+/// levels.levels.insert("db::migrations".to_owned(), Level::Info);
+/// levels.levels.insert("auth".to_owned(), Level::Warning);
+///
+/// assert_eq!(levels.level_for("db::pool"), Some(Level::Error));
+/// assert_eq!(levels.level_for("db::migrations"), Some(Level::Info));
+/// assert_eq!(levels.level_for("auth::session"), None);
+/// ```
+pub struct LoggerLevels {
+  /// The least severe level sent from loggers matching each pattern.
+  pub levels: HashMap<String, Level>,
+}
+
+impl LoggerLevels {
+  /// Creates levels that leave every logger at the client's minimum level.
+  pub fn new() -> LoggerLevels {
+    LoggerLevels::default()
+  }
+
+  /// The least severe level sent from `logger`, if a pattern matches it.
+  pub fn level_for(&self, logger: &str) -> Option<Level> {
+    self
+      .levels
+      .iter()
+      .filter(|&(pattern, _)| {
+        if pattern.ends_with('*') {
+          logger.starts_with(&pattern[..pattern.len() - 1])
+        } else {
+          logger == pattern
+        }
+      })
+      // A name beats a prefix of the same length.
+      .max_by_key(|&(pattern, _)| (pattern.len(), !pattern.ends_with('*')))
+      .map(|(_, level)| *level)
+  }
+}
@@ -0,0 +1,195 @@
+//! Provides `Hub`, which pairs a stack of scopes with the client events are sent through, following
+//! the unified Sentry SDK model.
+//!
+//! Every thread has a current hub, which the free functions in `scope` work on. It starts out as a copy
+//! of the top of `Hub::main()`, so a client bound (and a scope configured) on the main hub before
+//! threads start is picked up by all of them. Libraries can then capture through `Hub::current()`
+//! without knowing about the application's client, while applications use `Hub::run` to choose which
+//! hub (and so which client and DSN) is current for a piece of work.
+
+use std::cell::RefCell;
+use std::error::Error as StdError;
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+
+use Sentry;
+use models::{Event, EventId, Level};
+use scope::Scope;
+
+lazy_static! {
+  static ref MAIN_HUB: Arc<Hub> = Arc::new(Hub::new(None, Scope::new()));
+}
+
+thread_local! {
+  static THREAD_HUB: RefCell<Option<Arc<Hub>>> = RefCell::new(None);
+}
+
+/// A stack of scopes, and the client events captured through it are sent with.
+pub struct Hub {
+  client: RwLock<Option<Arc<Sentry>>>,
+  stack: Mutex<Vec<Scope>>,
+}
+
+/// Pops the scope it was created for when dropped, restoring the one below it.
+pub struct ScopeGuard {
+  hub: Arc<Hub>,
+  depth: usize,
+}
+
+impl Drop for ScopeGuard {
+  fn drop(&mut self) {
+    // Guards dropped out of order pop everything above their own scope too.
+    self.hub.lock_stack().truncate(self.depth);
+  }
+}
+
+/// Puts back the threads previous hub once `Hub::run` is done, even if it panics.
+struct RunGuard {
+  previous: Option<Arc<Hub>>,
+}
+
+impl Drop for RunGuard {
+  fn drop(&mut self) {
+    let previous = self.previous.take();
+    THREAD_HUB.with(|hub| *hub.borrow_mut() = previous);
+  }
+}
+
+impl Hub {
+  /// Creates a hub sending through `client`, with `scope` as its only scope.
+  pub fn new(client: Option<Arc<Sentry>>, scope: Scope) -> Hub {
+    Hub {
+      client: RwLock::new(client),
+      stack: Mutex::new(vec![scope]),
+    }
+  }
+
+  /// Creates a hub with the same client as `other`, and a copy of its current scope.
+  pub fn new_from_top(other: &Hub) -> Hub {
+    Hub::new(other.client(), other.current_scope())
+  }
+
+  /// The process wide hub every threads hub starts out as a copy of.
+  pub fn main() -> Arc<Hub> {
+    MAIN_HUB.clone()
+  }
+
+  /// The current threads hub.
+  pub fn current() -> Arc<Hub> {
+    THREAD_HUB.with(|hub| {
+      hub
+        .borrow_mut()
+        .get_or_insert_with(|| Arc::new(Hub::new_from_top(&MAIN_HUB)))
+        .clone()
+    })
+  }
+
+  /// Makes `hub` the current threads hub while running `f`.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// use sentry_rs::hub::Hub;
+  /// use std::sync::Arc;
+  ///
+  /// let hub = Arc::new(Hub::new_from_top(&Hub::current()));
+  /// Hub::run(hub.clone(), || {
+  ///   sentry_rs::scope::configure_scope(|scope| scope.set_tag("tenant", "acme"));
+  /// });
+  /// assert_eq!(hub.current_scope().tags.get("tenant"), Some(&"acme".to_owned()));
+  /// assert!(Hub::current().current_scope().tags.get("tenant").is_none());
+  /// ```
+  pub fn run<F: FnOnce() -> R, R>(hub: Arc<Hub>, f: F) -> R {
+    let previous = Hub::current();
+    THREAD_HUB.with(|current| *current.borrow_mut() = Some(hub));
+    let _guard = RunGuard {
+      previous: Some(previous),
+    };
+    f()
+  }
+
+  /// The client events are sent through, if one is bound.
+  pub fn client(&self) -> Option<Arc<Sentry>> {
+    match self.client.read() {
+      Ok(guard) => guard.clone(),
+      Err(poisoned) => poisoned.into_inner().clone(),
+    }
+  }
+
+  /// Sets the client events are sent through. With no client, captured events are thrown away.
+  pub fn bind_client(&self, client: Option<Arc<Sentry>>) {
+    let mut current = match self.client.write() {
+      Ok(guard) => guard,
+      Err(poisoned) => poisoned.into_inner(),
+    };
+    *current = client;
+  }
+
+  /// Locks the scope stack.
+  fn lock_stack(&self) -> MutexGuard<Vec<Scope>> {
+    match self.stack.lock() {
+      Ok(guard) => guard,
+      Err(poisoned) => poisoned.into_inner(),
+    }
+  }
+
+  /// Returns a copy of this hubs current scope.
+  pub fn current_scope(&self) -> Scope {
+    self.lock_stack().last().cloned().unwrap_or_default()
+  }
+
+  /// Changes this hubs current scope.
+  pub fn configure_scope<F: FnOnce(&mut Scope)>(&self, f: F) {
+    let mut stack = self.lock_stack();
+    if stack.is_empty() {
+      stack.push(Scope::new());
+    }
+    let last = stack.len() - 1;
+    f(&mut stack[last]);
+  }
+
+  /// Makes `scope` the current scope of `hub` until the returned guard is dropped.
+  pub fn bind_scope(hub: &Arc<Hub>, scope: Scope) -> ScopeGuard {
+    let mut stack = hub.lock_stack();
+    let depth = stack.len();
+    stack.push(scope);
+    ScopeGuard {
+      hub: hub.clone(),
+      depth: depth,
+    }
+  }
+
+  /// Sends an event through this hubs client with its current scope added, returning the events id.
+  /// Returns `None` without a client, or if the event was skipped, such as by the sample rate, or couldn't
+  /// be queued.
+  pub fn capture_event(&self, mut event: Event) -> Option<EventId> {
+    let client = match self.client() {
+      Some(client) => client,
+      None => return None,
+    };
+    self.current_scope().apply_to_event(&mut event);
+    let event_id = event.event_id.clone();
+    match client.queue_event(event) {
+      Ok(true) => Some(event_id),
+      _ => None,
+    }
+  }
+
+  /// Sends a message through this hubs client at `level`. Returns `None` without a client.
+  pub fn capture_message(&self, message: &str, level: Level) -> Option<EventId> {
+    let event = match self.client() {
+      Some(client) => client.create_event("hub", level.as_str(), message, None, None, None),
+      None => return None,
+    };
+    self.capture_event(event)
+  }
+
+  /// Sends `err` through this hubs client at the error level, with the chain of errors that caused it as
+  /// its exceptions, see `Sentry::log_error`. Returns `None` without a client.
+  pub fn capture_error<E: StdError + 'static>(&self, err: &E) -> Option<EventId> {
+    let event = match self.client() {
+      Some(client) => client.error_event("hub", err, None),
+      None => return None,
+    };
+    self.capture_event(event)
+  }
+}
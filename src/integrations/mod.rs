@@ -0,0 +1,169 @@
+//! Integrations between Sentry and other crates. Integrations with crates we don't already depend on
+//! live behind a cargo feature named after that crate, so you only pay for the ones you use. The `signal`
+//! crash handler is only on Unix, and the `seh` crash handler only on Windows.
+
+pub mod hyper;
+pub mod oom;
+pub mod process;
+#[cfg(windows)]
+pub mod seh;
+#[cfg(unix)]
+pub mod signal;
+#[cfg(feature = "iron")]
+pub mod iron;
+#[cfg(feature = "log4rs")]
+pub mod log4rs;
+#[cfg(feature = "slog")]
+pub mod slog;
+#[cfg(feature = "tracing")]
+pub mod tracing;
+
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use Sentry;
+use error::SendError;
+use models::Event;
+use snapshot::{is_redacted_header, REDACTED};
+
+/// Collects the headers of a request being reported, redacting the values of those that look like secrets,
+/// such as `Authorization` and `Cookie`.
+fn redact_headers<I: IntoIterator<Item = (String, String)>>(headers: I) -> HashMap<String, String> {
+  headers
+    .into_iter()
+    .map(|(name, value)| {
+      let value = if is_redacted_header(&name) { REDACTED.to_owned() } else { value };
+      (name, value)
+    })
+    .collect()
+}
+
+/// Redacts the values of the parameters in a query string whose names look like secrets, the same ones
+/// `redact_headers` redacts.
+fn redact_query(query: &str) -> String {
+  query
+    .split('&')
+    .map(|param| match param.find('=') {
+      Some(index) if is_redacted_header(&param[..index]) => format!("{}={}", &param[..index], REDACTED),
+      _ => param.to_owned(),
+    })
+    .collect::<Vec<_>>()
+    .join("&")
+}
+
+/// An event serialized ahead of time, for reporting from where one can't be built, such as a signal handler.
+/// Stamping it with a new id and the current time only writes over bytes that are already there.
+struct Prebuilt {
+  event: Event,
+  body: String,
+  event_id_at: usize,
+  timestamp_at: usize,
+}
+
+impl Prebuilt {
+  /// Serializes `event` the way `sentry` would send it.
+  fn new(sentry: &Sentry, mut event: Event) -> Result<Prebuilt, SendError> {
+    let body = Sentry::serialize(&mut event, &sentry.delivery)?;
+    let event_id_at = find_value(&body, "event_id", &event.event_id);
+    let timestamp_at = find_value(&body, "timestamp", &event.timestamp);
+    match (event_id_at, timestamp_at) {
+      (Some(event_id_at), Some(timestamp_at)) => Ok(Prebuilt {
+        event: event,
+        body: body,
+        event_id_at: event_id_at,
+        timestamp_at: timestamp_at,
+      }),
+      _ => Err(SendError::Serialization("the event id or timestamp wasn't where it was expected".to_owned())),
+    }
+  }
+
+  /// Gives the event a new id, made unique by `count`, and the current time, without allocating.
+  fn stamp(&mut self, count: u32) {
+    // The last eight hex digits of the id are replaced, so each stamp is a distinct event to Sentry.
+    let id_len = self.event.event_id.len();
+    let count_at = id_len.saturating_sub(8);
+    unsafe {
+      write_digits(&mut self.event.event_id.as_bytes_mut()[count_at..], u64::from(count), 16);
+      let body = self.body.as_bytes_mut();
+      write_digits(&mut body[self.event_id_at + count_at..self.event_id_at + id_len], u64::from(count), 16);
+      let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|since| since.as_secs()).unwrap_or(0);
+      let timestamp_len = self.event.timestamp.len();
+      write_timestamp(&mut body[self.timestamp_at..self.timestamp_at + timestamp_len], secs);
+      self.event.timestamp.as_bytes_mut().copy_from_slice(&body[self.timestamp_at..self.timestamp_at + timestamp_len]);
+    }
+  }
+
+  /// Writes `value` as a JSON number over the `width` bytes of the body starting at `at`, followed by the
+  /// spaces left over, which JSON allows after a value. `width` must fit the number.
+  fn write_number(&mut self, at: usize, width: usize, value: u64) {
+    let mut digits = [0; 20];
+    let mut len = 0;
+    let mut rest = value;
+    loop {
+      digits[len] = b'0' + (rest % 10) as u8;
+      len += 1;
+      rest /= 10;
+      if rest == 0 {
+        break;
+      }
+    }
+    let field = unsafe { &mut self.body.as_bytes_mut()[at..at + width] };
+    for (index, byte) in field.iter_mut().enumerate() {
+      *byte = if index < len { digits[len - 1 - index] } else { b' ' };
+    }
+  }
+}
+
+/// Where the value of the string field `name` starts in `body`, if it's there with `value`.
+fn find_value(body: &str, name: &str, value: &str) -> Option<usize> {
+  let field = format!("\"{}\":\"{}\"", name, value);
+  body.find(&field).map(|at| at + field.len() - value.len() - 1)
+}
+
+/// Writes `value` in `radix` over all of `field`, padded with leading zeros. Only ASCII is written.
+fn write_digits(field: &mut [u8], mut value: u64, radix: u64) {
+  for digit in field.iter_mut().rev() {
+    *digit = b"0123456789abcdef"[(value % radix) as usize];
+    value /= radix;
+  }
+}
+
+/// Writes `secs` since the epoch over `field` in UTC, as `%Y-%m-%dT%H:%M:%S`, the format event timestamps
+/// are sent in. `field` must be 19 bytes long.
+fn write_timestamp(field: &mut [u8], secs: u64) {
+  // Howard Hinnant's days to civil date algorithm, for dates after the epoch.
+  let days = secs / 86_400;
+  let era = (days + 719_468) / 146_097;
+  let day_of_era = days + 719_468 - era * 146_097;
+  let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+  let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let shifted_month = (5 * day_of_year + 2) / 153;
+  let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+  let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+  let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+  let time = secs % 86_400;
+  field.copy_from_slice(b"0000-00-00T00:00:00");
+  write_digits(&mut field[0..4], year, 10);
+  write_digits(&mut field[5..7], month, 10);
+  write_digits(&mut field[8..10], day, 10);
+  write_digits(&mut field[11..13], time / 3600, 10);
+  write_digits(&mut field[14..16], time / 60 % 60, 10);
+  write_digits(&mut field[17..19], time % 60, 10);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::write_timestamp;
+  use chrono::{TimeZone, Utc};
+
+  #[test]
+  fn timestamps_are_written_like_chrono_formats_them() {
+    for &secs in [0, 951_782_400, 1_234_567_890, 4_102_444_799].iter() {
+      let mut field = [0; 19];
+      write_timestamp(&mut field, secs);
+      let expected = Utc.timestamp_opt(secs as i64, 0).unwrap().format("%Y-%m-%dT%H:%M:%S").to_string();
+      assert_eq!(String::from_utf8_lossy(&field), expected);
+    }
+  }
+}
@@ -0,0 +1,34 @@
+//! Provides `SdkMetrics`, a hook for piping the SDKs own counters into a metrics system such as
+//! Prometheus or statsd as they change.
+//!
+//! Where `Sentry::stats` gives a snapshot to poll, a `SdkMetrics` implementation is pushed every update
+//! as it happens. Every method does nothing by default, so implementations only need the ones they use.
+
+use std::time::Duration;
+
+/// Counts an event handed to the worker queue.
+pub const EVENTS_QUEUED: &str = "sentry.events.queued";
+/// Counts an event that couldn't be queued.
+pub const EVENTS_DROPPED: &str = "sentry.events.dropped";
+/// Counts an event Sentry accepted.
+pub const EVENTS_SENT: &str = "sentry.events.sent";
+/// Counts an event that failed to reach Sentry.
+pub const EVENTS_FAILED: &str = "sentry.events.failed";
+/// Counts an event written to the dead letter file.
+pub const DEAD_LETTERS_WRITTEN: &str = "sentry.dead_letters.written";
+/// Gauges how many events are waiting in the worker queue.
+pub const QUEUE_DEPTH: &str = "sentry.queue.depth";
+/// Times how long posting an event to Sentry took, whether or not it succeeded.
+pub const SEND_DURATION: &str = "sentry.send.duration";
+
+/// Receives the SDKs internal metrics, named by the constants in this module.
+pub trait SdkMetrics: Send + Sync {
+  /// Adds `value` to the counter `name`.
+  fn increment(&self, _name: &str, _value: u64) {}
+
+  /// Sets the gauge `name` to `value`.
+  fn gauge(&self, _name: &str, _value: u64) {}
+
+  /// Records that the operation `name` took `duration`.
+  fn timing(&self, _name: &str, _duration: Duration) {}
+}
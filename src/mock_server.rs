@@ -0,0 +1,358 @@
+//! A Sentry server to test against, running on this machine, so an application's Sentry setup can be
+//! tested end to end without a real Sentry project. Only built with the `mock_server` feature.
+//!
+//! `MockServer::start` serves the store (`/api/<project>/store/`) and envelope
+//! (`/api/<project>/envelope/`) endpoints on a port of its own, recording every request it gets. Each
+//! request is answered with the next response queued with `MockServer::respond_with`, such as
+//! `MockResponse::rate_limited`, and accepted once there are none. The server stops when it's dropped.
+
+use models::{Scheme, SentryCredentials};
+
+use futures::{Future, Stream};
+use futures::sync::oneshot;
+use hyper::{Error as HyperError, StatusCode};
+use hyper::server::{Http, Request, Response, Service};
+use serde_json::{self, Value};
+
+use std::collections::VecDeque;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::sync::mpsc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// The key the server's credentials send with.
+pub const MOCK_KEY: &'static str = "public";
+
+/// The project the server's credentials send to.
+pub const MOCK_PROJECT_ID: &'static str = "1";
+
+#[derive(Clone, Debug, PartialEq)]
+/// A request the server received.
+pub struct ReceivedRequest {
+  /// The request's method, such as `POST`.
+  pub method: String,
+  /// The request's path, such as `/api/1/store/`.
+  pub path: String,
+  /// The request's headers, in the order they were received.
+  pub headers: Vec<(String, String)>,
+  /// The request's body.
+  pub body: Vec<u8>,
+}
+
+impl ReceivedRequest {
+  /// The value of the header called `name`, ignoring case, if it was sent.
+  pub fn header(&self, name: &str) -> Option<&str> {
+    self
+      .headers
+      .iter()
+      .find(|&&(ref header, _)| header.eq_ignore_ascii_case(name))
+      .map(|&(_, ref value)| value.as_str())
+  }
+
+  /// The events sent in the request: its body for the store endpoint, and the `event` items of an
+  /// envelope. Events that aren't valid JSON are left out.
+  pub fn events(&self) -> Vec<Value> {
+    if self.path.ends_with("/store/") {
+      serde_json::from_slice(&self.body).into_iter().collect()
+    } else if self.path.ends_with("/envelope/") {
+      envelope_events(&self.body)
+    } else {
+      Vec::new()
+    }
+  }
+}
+
+/// The `event` items of an envelope: a header line, then each item's header line followed by its payload,
+/// which is `length` bytes long when its header says so, and runs to the end of the line otherwise.
+fn envelope_events(body: &[u8]) -> Vec<Value> {
+  let mut events = Vec::new();
+  let mut rest = match body.iter().position(|&b| b == b'\n') {
+    Some(at) => &body[at + 1..],
+    None => return events,
+  };
+  while !rest.is_empty() {
+    let header_end = rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len());
+    let header: Value = match serde_json::from_slice(&rest[..header_end]) {
+      Ok(header) => header,
+      Err(_) => break,
+    };
+    rest = rest.get(header_end + 1..).unwrap_or(&[]);
+    let length = header["length"]
+      .as_u64()
+      .map(|length| length as usize)
+      .unwrap_or_else(|| rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len()));
+    let payload = match rest.get(..length) {
+      Some(payload) => payload,
+      None => break,
+    };
+    if header["type"] == "event" {
+      if let Ok(event) = serde_json::from_slice(payload) {
+        events.push(event);
+      }
+    }
+    rest = rest.get(length + 1..).unwrap_or(&[]);
+  }
+  events
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A response the server answers a request with.
+pub struct MockResponse {
+  /// The response's status.
+  pub status: StatusCode,
+  /// Headers sent with the response.
+  pub headers: Vec<(String, String)>,
+  /// The response's body.
+  pub body: String,
+}
+
+impl MockResponse {
+  /// A response with `status`, and no headers or body.
+  pub fn new(status: StatusCode) -> MockResponse {
+    MockResponse {
+      status: status,
+      headers: Vec::new(),
+      body: String::new(),
+    }
+  }
+
+  /// A `429 Too Many Requests`, asking for nothing to be sent for `retry_after`.
+  pub fn rate_limited(retry_after: Duration) -> MockResponse {
+    let mut response = MockResponse::new(StatusCode::TooManyRequests);
+    response
+      .headers
+      .push(("Retry-After".to_owned(), retry_after.as_secs().to_string()));
+    response
+  }
+
+  /// A `500 Internal Server Error`.
+  pub fn server_error() -> MockResponse {
+    MockResponse::new(StatusCode::InternalServerError)
+  }
+
+  /// A `400 Bad Request` rejecting the event with `reason`, sent as `X-Sentry-Error` the way Sentry does.
+  pub fn rejected(reason: &str) -> MockResponse {
+    let mut response = MockResponse::new(StatusCode::BadRequest);
+    response.headers.push(("X-Sentry-Error".to_owned(), reason.to_owned()));
+    response.body = json!({ "detail": reason }).to_string();
+    response
+  }
+}
+
+/// What the server has received, and has been told to respond with, shared with its thread.
+#[derive(Default)]
+struct MockState {
+  received: Mutex<Vec<ReceivedRequest>>,
+  /// Signalled whenever a request is received.
+  changed: Condvar,
+  responses: Mutex<VecDeque<MockResponse>>,
+}
+
+impl MockState {
+  /// Locks the received requests.
+  fn received(&self) -> MutexGuard<Vec<ReceivedRequest>> {
+    match self.received.lock() {
+      Ok(guard) => guard,
+      Err(poisoned) => poisoned.into_inner(),
+    }
+  }
+
+  /// Locks the queued responses.
+  fn responses(&self) -> MutexGuard<VecDeque<MockResponse>> {
+    match self.responses.lock() {
+      Ok(guard) => guard,
+      Err(poisoned) => poisoned.into_inner(),
+    }
+  }
+
+  /// Records `request`, returning what it's answered with.
+  fn respond(&self, request: ReceivedRequest) -> MockResponse {
+    let sentry_endpoint = request.path.starts_with("/api/")
+      && (request.path.ends_with("/store/") || request.path.ends_with("/envelope/"));
+    let response = if !sentry_endpoint {
+      MockResponse::new(StatusCode::NotFound)
+    } else {
+      self.responses().pop_front().unwrap_or_else(|| {
+        let mut accepted = MockResponse::new(StatusCode::Ok);
+        let id = request.events().first().map_or(Value::Null, |event| event["event_id"].clone());
+        accepted.body = json!({ "id": id }).to_string();
+        accepted
+      })
+    };
+    self.received().push(request);
+    self.changed.notify_all();
+    response
+  }
+}
+
+/// Answers each request the server gets from its `MockState`.
+struct MockService {
+  state: Arc<MockState>,
+}
+
+impl Service for MockService {
+  type Request = Request;
+  type Response = Response;
+  type Error = HyperError;
+  type Future = Box<Future<Item = Response, Error = HyperError>>;
+
+  fn call(&self, request: Request) -> Self::Future {
+    let state = self.state.clone();
+    let (method, uri, _, headers, body) = request.deconstruct();
+    let headers = headers
+      .iter()
+      .map(|header| (header.name().to_owned(), header.value_string()))
+      .collect();
+    Box::new(body.concat2().map(move |body| {
+      let received = ReceivedRequest {
+        method: method.to_string(),
+        path: uri.path().to_owned(),
+        headers: headers,
+        body: body.to_vec(),
+      };
+      let answer = state.respond(received);
+      let mut response = Response::new().with_status(answer.status);
+      for (name, value) in answer.headers {
+        response.headers_mut().set_raw(name, value);
+      }
+      response.with_body(answer.body)
+    }))
+  }
+}
+
+/// A Sentry server running on this machine, stopped when it's dropped.
+///
+/// # Examples
+///
+/// ```rust
+/// use sentry_rs::Sentry;
+/// use sentry_rs::mock_server::{MockResponse, MockServer};
+/// use std::time::Duration;
+///
+/// let server = MockServer::start().unwrap();
+/// let sentry = Sentry::new("Server".to_owned(), "Release".to_owned(), "production".to_owned(), server.credentials());
+///
+/// server.respond_with(MockResponse::server_error());
+/// let event = sentry.create_event("test", "error", "Lost", None, None, None);
+/// assert!(sentry.capture_event_sync(event, Duration::from_secs(5)).is_err());
+///
+/// let event = sentry.create_event("test", "error", "Delivered", None, None, None);
+/// assert!(sentry.capture_event_sync(event, Duration::from_secs(5)).is_ok());
+/// let events = server.wait_for_events(2, Duration::from_secs(5));
+/// assert_eq!(events[1]["message"], "Delivered");
+/// ```
+pub struct MockServer {
+  addr: SocketAddr,
+  state: Arc<MockState>,
+  shutdown: Option<oneshot::Sender<()>>,
+  thread: Option<JoinHandle<()>>,
+}
+
+impl MockServer {
+  /// Starts a server on a free port of this machine, on a thread of its own.
+  pub fn start() -> io::Result<MockServer> {
+    let state = Arc::new(MockState::default());
+    let (started, starting) = mpsc::channel();
+    let (shutdown, shutdown_signal) = oneshot::channel::<()>();
+    let service_state = state.clone();
+    let thread = thread::spawn(move || {
+      let addr = SocketAddr::from(([127, 0, 0, 1], 0));
+      let new_service = move || {
+        Ok(MockService {
+          state: service_state.clone(),
+        })
+      };
+      let mut server = match Http::new().bind(&addr, new_service) {
+        Ok(server) => server,
+        Err(err) => {
+          let _ = started.send(Err(err));
+          return;
+        }
+      };
+      // Clients keep their connections open, so the server doesn't wait for them to close when stopped.
+      server.shutdown_timeout(Duration::from_secs(0));
+      let _ = started.send(server.local_addr());
+      let _ = server.run_until(shutdown_signal.then(|_| Ok(())));
+    });
+
+    let addr = match starting.recv() {
+      Ok(Ok(addr)) => addr,
+      Ok(Err(err)) => return Err(io::Error::new(io::ErrorKind::Other, err.to_string())),
+      Err(_) => return Err(io::Error::new(io::ErrorKind::Other, "the mock server's thread died")),
+    };
+    Ok(MockServer {
+      addr: addr,
+      state: state,
+      shutdown: Some(shutdown),
+      thread: Some(thread),
+    })
+  }
+
+  /// Where the server is listening.
+  pub fn addr(&self) -> SocketAddr {
+    self.addr
+  }
+
+  /// Credentials sending events to the server, with the key `MOCK_KEY` and project `MOCK_PROJECT_ID`.
+  pub fn credentials(&self) -> SentryCredentials {
+    SentryCredentials {
+      scheme: Scheme::Http,
+      key: MOCK_KEY.to_owned(),
+      secret: None,
+      host: Some(self.addr.to_string()),
+      project_id: MOCK_PROJECT_ID.to_owned(),
+    }
+  }
+
+  /// Answers the next request with `response`, after any queued before it.
+  pub fn respond_with(&self, response: MockResponse) {
+    self.state.responses().push_back(response);
+  }
+
+  /// Every request received so far, oldest first.
+  pub fn requests(&self) -> Vec<ReceivedRequest> {
+    self.state.received().clone()
+  }
+
+  /// Every event received so far, oldest first, including ones that were answered with an error.
+  pub fn events(&self) -> Vec<Value> {
+    self.state.received().iter().flat_map(ReceivedRequest::events).collect()
+  }
+
+  /// Waits up to `timeout` for at least `count` events to have been received, returning every event
+  /// received by then.
+  pub fn wait_for_events(&self, count: usize, timeout: Duration) -> Vec<Value> {
+    let deadline = Instant::now() + timeout;
+    let mut received = self.state.received();
+    loop {
+      let events = received.iter().flat_map(ReceivedRequest::events).collect::<Vec<_>>();
+      let now = Instant::now();
+      if events.len() >= count || now >= deadline {
+        return events;
+      }
+      received = match self.state.changed.wait_timeout(received, deadline - now) {
+        Ok((guard, _)) => guard,
+        Err(poisoned) => poisoned.into_inner().0,
+      };
+    }
+  }
+
+  /// Forgets every request received so far, and any responses still queued.
+  pub fn reset(&self) {
+    self.state.received().clear();
+    self.state.responses().clear();
+  }
+}
+
+impl Drop for MockServer {
+  fn drop(&mut self) {
+    if let Some(shutdown) = self.shutdown.take() {
+      let _ = shutdown.send(());
+    }
+    if let Some(thread) = self.thread.take() {
+      let _ = thread.join();
+    }
+  }
+}
@@ -0,0 +1,181 @@
+//! Detects the operating system and architecture events are sent from, for the `Device` model and the
+//! `os` and `device` contexts.
+//!
+//! Unix systems are asked through `uname`, with Linux distributions named from `/etc/os-release` when
+//! it's there. Windows is asked through `RtlGetVersion`, which unlike `GetVersionExW` isn't capped at the
+//! version the program's manifest declares support for, and `GetNativeSystemInfo`, which gives the
+//! machine's architecture even to a 32-bit program on 64-bit Windows. Detection only happens once per
+//! process.
+
+use serde_json::Value;
+
+use std::env;
+
+lazy_static! {
+  static ref OS_INFO: OsInfo = detect();
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+/// What's known about the operating system.
+pub struct OsInfo {
+  /// The name of the operating system, such as `Ubuntu`, `Darwin`, or `Windows`.
+  pub name: String,
+  /// The version of the operating system.
+  pub version: Option<String>,
+  /// The build of the operating system.
+  pub build: Option<String>,
+  /// The version of the kernel.
+  pub kernel_version: Option<String>,
+  /// The CPU architecture, such as `x86_64`.
+  pub arch: String,
+}
+
+impl OsInfo {
+  /// The `os` context for this operating system.
+  pub fn os_context(&self) -> Value {
+    let mut context = json!({
+      "type": "os",
+      "name": self.name,
+    });
+    if let Some(ref version) = self.version {
+      context["version"] = json!(version);
+    }
+    if let Some(ref build) = self.build {
+      context["build"] = json!(build);
+    }
+    if let Some(ref kernel_version) = self.kernel_version {
+      context["kernel_version"] = json!(kernel_version);
+    }
+    context
+  }
+
+  /// The `device` context for this machine.
+  pub fn device_context(&self) -> Value {
+    json!({
+      "type": "device",
+      "arch": self.arch,
+    })
+  }
+}
+
+/// Returns what's known about the operating system this process is running on.
+pub fn os_info() -> &'static OsInfo {
+  &OS_INFO
+}
+
+#[cfg(unix)]
+fn detect() -> OsInfo {
+  use libc;
+  use std::ffi::CStr;
+  use std::mem;
+
+  let mut info = fallback();
+  unsafe {
+    let mut uts: libc::utsname = mem::zeroed();
+    if libc::uname(&mut uts) == 0 {
+      let field = |chars: &[libc::c_char]| CStr::from_ptr(chars.as_ptr()).to_string_lossy().into_owned();
+      info.name = field(&uts.sysname);
+      info.version = Some(field(&uts.release));
+      info.build = Some(field(&uts.version));
+      info.kernel_version = Some(field(&uts.release));
+      info.arch = field(&uts.machine);
+    }
+  }
+  if cfg!(target_os = "linux") {
+    if let Some((name, version)) = linux_distribution() {
+      info.name = name;
+      info.version = version;
+    }
+  }
+  info
+}
+
+#[cfg(windows)]
+#[link(name = "ntdll")]
+extern "system" {
+  /// Fills in `info`, returning `0` (`STATUS_SUCCESS`). Not declared by `winapi`.
+  fn RtlGetVersion(info: *mut ::winapi::um::winnt::OSVERSIONINFOW) -> i32;
+}
+
+#[cfg(windows)]
+fn detect() -> OsInfo {
+  use std::mem;
+  use winapi::um::sysinfoapi::{GetNativeSystemInfo, SYSTEM_INFO};
+  use winapi::um::winnt::{OSVERSIONINFOW, PROCESSOR_ARCHITECTURE_AMD64, PROCESSOR_ARCHITECTURE_ARM};
+  use winapi::um::winnt::{PROCESSOR_ARCHITECTURE_ARM64, PROCESSOR_ARCHITECTURE_IA64, PROCESSOR_ARCHITECTURE_INTEL};
+
+  let mut info = fallback();
+  info.name = "Windows".to_owned();
+  unsafe {
+    let mut system: SYSTEM_INFO = mem::zeroed();
+    GetNativeSystemInfo(&mut system);
+    let arch = match system.u.s().wProcessorArchitecture {
+      PROCESSOR_ARCHITECTURE_AMD64 => Some("x86_64"),
+      PROCESSOR_ARCHITECTURE_INTEL => Some("x86"),
+      PROCESSOR_ARCHITECTURE_ARM64 => Some("aarch64"),
+      PROCESSOR_ARCHITECTURE_ARM => Some("arm"),
+      PROCESSOR_ARCHITECTURE_IA64 => Some("ia64"),
+      _ => None,
+    };
+    if let Some(arch) = arch {
+      info.arch = arch.to_owned();
+    }
+
+    let mut version: OSVERSIONINFOW = mem::zeroed();
+    version.dwOSVersionInfoSize = mem::size_of::<OSVERSIONINFOW>() as u32;
+    if RtlGetVersion(&mut version) == 0 {
+      info.version = Some(format!("{}.{}", version.dwMajorVersion, version.dwMinorVersion));
+      info.build = Some(version.dwBuildNumber.to_string());
+      info.kernel_version = Some(format!(
+        "{}.{}.{}",
+        version.dwMajorVersion, version.dwMinorVersion, version.dwBuildNumber
+      ));
+    }
+  }
+  info
+}
+
+#[cfg(not(any(unix, windows)))]
+fn detect() -> OsInfo {
+  fallback()
+}
+
+/// What can be told about the operating system without asking it.
+fn fallback() -> OsInfo {
+  OsInfo {
+    name: env::consts::OS.to_owned(),
+    version: None,
+    build: None,
+    kernel_version: None,
+    arch: env::consts::ARCH.to_owned(),
+  }
+}
+
+/// The name and version of the Linux distribution, from `/etc/os-release`.
+#[cfg(unix)]
+fn linux_distribution() -> Option<(String, Option<String>)> {
+  use std::fs::File;
+  use std::io::{BufRead, BufReader};
+
+  let file = match File::open("/etc/os-release") {
+    Ok(file) => file,
+    Err(_) => return None,
+  };
+  let mut name = None;
+  let mut version = None;
+  for line in BufReader::new(file).lines() {
+    let line = match line {
+      Ok(line) => line,
+      Err(_) => break,
+    };
+    let mut parts = line.splitn(2, '=');
+    let key = parts.next().unwrap_or("");
+    let value = parts.next().unwrap_or("").trim_matches('"').to_owned();
+    match key {
+      "NAME" => name = Some(value),
+      "VERSION_ID" => version = Some(value),
+      _ => {}
+    }
+  }
+  name.map(|name| (name, version))
+}
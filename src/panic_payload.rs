@@ -0,0 +1,122 @@
+//! Turns panic payloads into event messages, and lets applications teach it about their own payload
+//! types.
+//!
+//! `panic!()` produces a `&'static str` or `String` payload, which is used as the message as is. Anything
+//! else, such as a value passed to `std::panic::panic_any`, becomes `Box<Any>` unless an extractor for
+//! its type is registered. An extractor returns the message to use, along with extras added to the
+//! event. Extractors are shared by the whole process, and tried in the order they were registered. They
+//! run inside the panic hook, so they mustn't panic themselves.
+
+use serde_json::Value;
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// Turns a panic payload into a `PanicPayload`, or `None` if it isn't a type this extractor knows.
+pub type PayloadExtractor = Box<Fn(&(Any + Send)) -> Option<PanicPayload> + Send + Sync>;
+
+lazy_static! {
+  static ref EXTRACTORS: RwLock<Vec<PayloadExtractor>> = RwLock::new(Vec::new());
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// What a panic payload says about the panic.
+pub struct PanicPayload {
+  /// The message of the event sent for the panic.
+  pub message: String,
+  /// Extras added to the event, unless it already has an extra with the same key.
+  pub extra: HashMap<String, Value>,
+}
+
+impl PanicPayload {
+  /// Creates a payload with a message, and no extras.
+  pub fn new(message: &str) -> PanicPayload {
+    PanicPayload {
+      message: message.to_owned(),
+      extra: HashMap::new(),
+    }
+  }
+
+  /// Adds an extra to the event sent for the panic.
+  pub fn with_extra(mut self, key: &str, value: Value) -> PanicPayload {
+    self.extra.insert(key.to_owned(), value);
+    self
+  }
+}
+
+/// Registers an extractor tried on every payload that isn't a string. Extractors registered before it
+/// get the first try.
+pub fn register_extractor<F>(f: F)
+where
+  F: Fn(&(Any + Send)) -> Option<PanicPayload> + Send + Sync + 'static,
+{
+  let mut extractors = match EXTRACTORS.write() {
+    Ok(guard) => guard,
+    Err(poisoned) => poisoned.into_inner(),
+  };
+  extractors.push(Box::new(f));
+}
+
+/// Registers an extractor for payloads of type `T`.
+///
+/// # Examples
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate serde_json;
+/// extern crate sentry_rs;
+///
+/// use sentry_rs::panic_payload::{self, PanicPayload};
+///
+/// struct OrderFailed {
+///   order_id: u64,
+/// }
+///
+/// fn main() {
+///   panic_payload::register_type(|failed: &OrderFailed| {
+///     PanicPayload::new("Order failed").with_extra("order_id", json!(failed.order_id))
+///   });
+///
+///   let payload: Box<std::any::Any + Send> = Box::new(OrderFailed { order_id: 42 });
+///   let extracted = panic_payload::extract(&*payload);
+///   assert_eq!(extracted.message, "Order failed");
+///   assert_eq!(extracted.extra["order_id"], json!(42));
+/// }
+/// ```
+pub fn register_type<T, F>(f: F)
+where
+  T: Any,
+  F: Fn(&T) -> PanicPayload + Send + Sync + 'static,
+{
+  register_extractor(move |payload: &(Any + Send)| payload.downcast_ref::<T>().map(&f));
+}
+
+/// Removes every registered extractor.
+pub fn clear_extractors() {
+  let mut extractors = match EXTRACTORS.write() {
+    Ok(guard) => guard,
+    Err(poisoned) => poisoned.into_inner(),
+  };
+  extractors.clear();
+}
+
+/// What `payload` says about its panic: its text for a string, what the first extractor that knows it
+/// returns otherwise, or just `Box<Any>`.
+pub fn extract(payload: &(Any + Send)) -> PanicPayload {
+  if let Some(s) = payload.downcast_ref::<&'static str>() {
+    return PanicPayload::new(s);
+  }
+  if let Some(s) = payload.downcast_ref::<String>() {
+    return PanicPayload::new(s);
+  }
+  let extractors = match EXTRACTORS.read() {
+    Ok(guard) => guard,
+    Err(poisoned) => poisoned.into_inner(),
+  };
+  extractors
+    .iter()
+    .filter_map(|extractor| extractor(payload))
+    .next()
+    .unwrap_or_else(|| PanicPayload::new("Box<Any>"))
+}
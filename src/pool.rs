@@ -0,0 +1,59 @@
+//! Provides `BufferPool`, a few byte buffers kept around to serialize events into.
+//!
+//! Serializing an event into a fresh buffer grows it several times over. A buffer from the pool has
+//! usually already grown to the size of an event, so only the finished payload needs allocating.
+
+use std::sync::Mutex;
+
+/// A pool of byte buffers that are handed out empty and given back once used.
+pub struct BufferPool {
+  buffers: Mutex<Vec<Vec<u8>>>,
+  /// The most buffers kept waiting in the pool.
+  max_buffers: usize,
+  /// Buffers that have grown past this many bytes are dropped instead of kept, so one huge event
+  /// doesn't hold on to its memory.
+  max_capacity: usize,
+}
+
+impl BufferPool {
+  /// Creates an empty pool keeping at most `max_buffers` buffers of up to `max_capacity` bytes.
+  pub fn new(max_buffers: usize, max_capacity: usize) -> BufferPool {
+    BufferPool {
+      buffers: Mutex::new(Vec::new()),
+      max_buffers: max_buffers,
+      max_capacity: max_capacity,
+    }
+  }
+
+  /// An empty buffer, from the pool if it has one.
+  pub fn take(&self) -> Vec<u8> {
+    let mut buffers = match self.buffers.lock() {
+      Ok(guard) => guard,
+      Err(poisoned) => poisoned.into_inner(),
+    };
+    buffers.pop().unwrap_or_else(Vec::new)
+  }
+
+  /// Gives a buffer back to the pool, emptied, unless the pool is full or the buffer has grown too big.
+  pub fn give(&self, mut buffer: Vec<u8>) {
+    if buffer.capacity() > self.max_capacity {
+      return;
+    }
+    buffer.clear();
+    let mut buffers = match self.buffers.lock() {
+      Ok(guard) => guard,
+      Err(poisoned) => poisoned.into_inner(),
+    };
+    if buffers.len() < self.max_buffers {
+      buffers.push(buffer);
+    }
+  }
+
+  /// How many buffers are waiting in the pool.
+  pub fn len(&self) -> usize {
+    match self.buffers.lock() {
+      Ok(guard) => guard.len(),
+      Err(poisoned) => poisoned.into_inner().len(),
+    }
+  }
+}
@@ -11,6 +11,7 @@
 //! The implicit event loop is crated lazily on first use of these implementations, meaning that advanced
 //! users who wish to interface more directly with tokio can do so by using the otherwise provided
 //! implementations without causing any extra background threads or event loops to be spawned.
+//! It runs until `shutdown` is called, and is started again if it's needed after that.
 //!
 //! This file is mostly borrowed from [Rusoto](https://github.com/rusoto/rusoto)
 //! who is also licensed under MIT, and whose license is available:
@@ -18,8 +19,10 @@
 
 use std::io::Result as IoResult;
 use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
-use std::thread;
+use std::thread::{self, JoinHandle};
 
 use futures::{Async, Future, Poll, Stream};
 use futures::future::{ok, Either};
@@ -27,23 +30,67 @@ use futures::sync::{mpsc, oneshot};
 use hyper::Request as HyperRequest;
 use tokio_core::reactor::{Core, Handle, Remote};
 
-use request::{DispatchRequest, HttpClient, HttpDispatchError, HttpResponse, HttpsClient, TlsError};
+use diagnostics;
+use request::{ClientOptions, DispatchErrorKind, DispatchRequest, HttpDispatchError, HttpResponse};
+use request::{HttpClient, HttpsClient, TlsError};
 
 lazy_static! {
-    static ref DEFAULT_REACTOR: Reactor = {
-      Reactor::spawn().expect("failed to spawn default reactor")
-    };
+    static ref DEFAULT_REACTOR: Mutex<Option<DefaultReactor>> = Mutex::new(None);
 }
 
+/// The implicit event loop, and what's needed to stop it.
+struct DefaultReactor {
+  reactor: Reactor,
+  running: Arc<AtomicBool>,
+  thread: JoinHandle<()>,
+}
+
+/// The implicit event loop, spawning it if it isn't running.
+fn default_reactor() -> IoResult<Reactor> {
+  let mut default = match DEFAULT_REACTOR.lock() {
+    Ok(guard) => guard,
+    Err(poisoned) => poisoned.into_inner(),
+  };
+  if let Some(ref default) = *default {
+    return Ok(default.reactor.clone());
+  }
+  let spawned = Reactor::spawn()?;
+  let reactor = spawned.reactor.clone();
+  *default = Some(spawned);
+  Ok(reactor)
+}
+
+/// Stops the implicit event loop, and waits for its thread to exit.
+///
+/// Requests still in flight on it fail, as does anything sent through a `RequestDispatcher` created on
+/// it before now. So `Sentry::close` every client first. Dispatchers created afterwards start a new
+/// event loop.
+pub fn shutdown() {
+  let default = match DEFAULT_REACTOR.lock() {
+    Ok(mut guard) => guard.take(),
+    Err(poisoned) => poisoned.into_inner().take(),
+  };
+  if let Some(default) = default {
+    default.running.store(false, Ordering::SeqCst);
+    // Wakes the event loop up, so it notices it should stop.
+    default.reactor.remote.spawn(|_| ok(()));
+    let _ = default.thread.join();
+  }
+}
+
+#[derive(Clone)]
 struct Reactor {
   remote: Remote,
 }
 
 impl Reactor {
-  fn spawn() -> IoResult<Reactor> {
+  fn spawn() -> IoResult<DefaultReactor> {
     let (init_tx, init_rx) = oneshot::channel();
+    let running = Arc::new(AtomicBool::new(true));
+    let still_running = running.clone();
 
-    thread::spawn(move || {
+    let thread = thread::spawn(move || {
+      let _internal = diagnostics::enter();
       let mut core = match Core::new() {
         Ok(core) => {
           if let Err(_) = init_tx.send(Ok(core.remote())) {
@@ -59,21 +106,25 @@ impl Reactor {
         }
       };
 
-      loop {
+      while still_running.load(Ordering::SeqCst) {
         core.turn(None);
       }
     });
 
     let remote = init_rx.wait().expect("failed to initiate reactor")?;
-    Ok(Reactor { remote: remote })
+    Ok(DefaultReactor {
+      reactor: Reactor { remote: remote },
+      running: running,
+      thread: thread,
+    })
   }
 
-  fn default_secure_request_dispatcher(&self) -> Result<RequestDispatcher, TlsError> {
-    self.new_request_dispatcher(|handle| HttpsClient::new(&handle))
+  fn default_secure_request_dispatcher(&self, options: ClientOptions) -> Result<RequestDispatcher, TlsError> {
+    self.new_request_dispatcher(move |handle| HttpsClient::with_options(&handle, options))
   }
 
-  fn default_request_dispatcher(&self) -> Result<RequestDispatcher, ()> {
-    self.new_request_dispatcher(|handle| HttpClient::new(&handle))
+  fn default_request_dispatcher(&self, options: ClientOptions) -> Result<RequestDispatcher, ()> {
+    self.new_request_dispatcher(move |handle| HttpClient::with_options(&handle, options))
   }
 
   fn new_request_dispatcher<
@@ -151,7 +202,9 @@ impl Reactor {
   }
 }
 
-/// A request dispatcher backed by an implicit event loop.
+/// A request dispatcher backed by an implicit event loop. Clones share the same client, and so its
+/// connections.
+#[derive(Clone)]
 pub struct RequestDispatcher {
   sender: mpsc::UnboundedSender<
     (
@@ -163,18 +216,50 @@ pub struct RequestDispatcher {
 
 impl Default for RequestDispatcher {
   fn default() -> RequestDispatcher {
-    DEFAULT_REACTOR
-      .default_secure_request_dispatcher()
-      .expect("failed to create default request dispatcher")
+    RequestDispatcher::with_options(ClientOptions::default())
   }
 }
 
 impl RequestDispatcher {
   pub fn default_non_secure() -> RequestDispatcher {
-    DEFAULT_REACTOR
-      .default_request_dispatcher()
+    RequestDispatcher::non_secure_with_options(ClientOptions::default())
+  }
+
+  /// Creates a tls-enabled dispatcher whose client manages its connections as `options` says.
+  pub fn with_options(options: ClientOptions) -> RequestDispatcher {
+    default_reactor()
+      .expect("failed to spawn default reactor")
+      .default_secure_request_dispatcher(options)
+      .expect("failed to create default request dispatcher")
+  }
+
+  /// Creates a non-tls-enabled dispatcher whose client manages its connections as `options` says.
+  pub fn non_secure_with_options(options: ClientOptions) -> RequestDispatcher {
+    default_reactor()
+      .expect("failed to spawn default reactor")
+      .default_request_dispatcher(options)
       .expect("failed to create default non-secure request dispatcher")
   }
+
+  /// Creates a tls-enabled dispatcher on the event loop behind `remote`, instead of the implicit one.
+  ///
+  /// This blocks until that event loop has set the client up, so it must not be called from the
+  /// thread running it.
+  pub fn on_remote(remote: &Remote, options: ClientOptions) -> RequestDispatcher {
+    Reactor { remote: remote.clone() }
+      .default_secure_request_dispatcher(options)
+      .expect("failed to create request dispatcher")
+  }
+
+  /// Creates a non-tls-enabled dispatcher on the event loop behind `remote`, instead of the implicit one.
+  ///
+  /// This blocks until that event loop has set the client up, so it must not be called from the
+  /// thread running it.
+  pub fn non_secure_on_remote(remote: &Remote, options: ClientOptions) -> RequestDispatcher {
+    Reactor { remote: remote.clone() }
+      .default_request_dispatcher(options)
+      .expect("failed to create non-secure request dispatcher")
+  }
 }
 
 /// Future returned from `RequestDispatcher`.
@@ -187,13 +272,13 @@ impl Future for RequestDispatcherFuture {
   type Error = HttpDispatchError;
 
   fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-    match self
-      .receiver
-      .poll()
-      .expect("failed to retrieve response from reactor")
-    {
-      Async::NotReady => Ok(Async::NotReady),
-      Async::Ready(result) => result.map(Async::Ready),
+    match self.receiver.poll() {
+      Ok(Async::NotReady) => Ok(Async::NotReady),
+      Ok(Async::Ready(result)) => result.map(Async::Ready),
+      Err(oneshot::Canceled) => Err(HttpDispatchError::new(
+        DispatchErrorKind::Other,
+        "the event loop shut down before the request finished",
+      )),
     }
   }
 }
@@ -203,9 +288,8 @@ impl DispatchRequest for RequestDispatcher {
 
   fn dispatch(&self, request: HyperRequest, timeout: Option<Duration>) -> Self::Future {
     let (tx, rx) = oneshot::channel();
-    if let Some(err) = self.sender.unbounded_send(((request, timeout), tx)).err() {
-      panic!("failed to send request to reactor: {}", err);
-    }
+    // If the event loop has shut down, the request is dropped along with `tx`, and the future fails.
+    let _ = self.sender.unbounded_send(((request, timeout), tx));
     RequestDispatcherFuture { receiver: rx }
   }
 }
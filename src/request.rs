@@ -13,15 +13,22 @@ use hyper::Client as HyperClient;
 use hyper::client::FutureResponse as HyperFutureResponse;
 use hyper::{Request as HyperRequest, Response as HyperResponse};
 use hyper::Error as HyperError;
-use hyper::header::Headers as HyperHeaders;
+use hyper::header::{ContentType, Headers as HyperHeaders};
+use hyper::mime;
 use hyper::StatusCode;
-use hyper::client::HttpConnector;
 use hyper_tls::HttpsConnector;
+use native_tls::TlsConnector;
+use serde::de::DeserializeOwned;
+use serde_json;
 use tokio_core::reactor::{Handle, Timeout};
 
-use std::io::Error as IoError;
+use resolve::{Resolve, ResolvingConnector};
+use snapshot::{is_redacted_header, REDACTED};
+
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
 use std::error::Error;
 use std::fmt;
+use std::sync::Arc;
 use std::time::Duration;
 
 /// Stores the response from a HTTP request.
@@ -44,6 +51,96 @@ pub struct BufferedHttpResponse {
   pub headers: HyperHeaders,
 }
 
+impl BufferedHttpResponse {
+  /// The charset of the body from its `Content-Type`, in lowercase, or `utf-8` if it doesn't name one.
+  pub fn charset(&self) -> String {
+    self
+      .headers
+      .get::<ContentType>()
+      .and_then(|content_type| content_type.get_param(mime::CHARSET))
+      .map(|charset| charset.as_str().to_lowercase())
+      .unwrap_or_else(|| "utf-8".to_owned())
+  }
+
+  /// The body as text, decoded with its charset. Only UTF-8, US-ASCII, and ISO-8859-1 are understood.
+  ///
+  /// # Examples
+  ///
+  /// ```rust
+  /// extern crate hyper;
+  /// extern crate sentry_rs;
+  ///
+  /// use hyper::StatusCode;
+  /// use hyper::header::{ContentType, Headers};
+  /// use sentry_rs::request::BufferedHttpResponse;
+  ///
+  /// fn main() {
+  ///   let mut headers = Headers::new();
+  ///   headers.set(ContentType("text/plain; charset=ISO-8859-1".parse().unwrap()));
+  ///   let response = BufferedHttpResponse {
+  ///     status: StatusCode::Ok,
+  ///     body: b"Caf\xe9".to_vec(),
+  ///     headers: headers,
+  ///   };
+  ///   assert_eq!(response.text().unwrap(), "Café");
+  /// }
+  /// ```
+  pub fn text(&self) -> Result<String, BodyError> {
+    let charset = self.charset();
+    match charset.as_str() {
+      "utf-8" | "utf8" => String::from_utf8(self.body.clone()).map_err(|_| BodyError::InvalidText(charset)),
+      "us-ascii" | "ascii" if self.body.is_ascii() => Ok(self.body.iter().map(|&b| b as char).collect()),
+      "us-ascii" | "ascii" => Err(BodyError::InvalidText(charset)),
+      "iso-8859-1" | "latin1" => Ok(self.body.iter().map(|&b| b as char).collect()),
+      _ => Err(BodyError::UnsupportedCharset(charset)),
+    }
+  }
+
+  /// The body as text, with anything that can't be decoded replaced by `U+FFFD`. Bodies in a charset
+  /// `text` doesn't understand are read as UTF-8.
+  pub fn text_lossy(&self) -> String {
+    self
+      .text()
+      .unwrap_or_else(|_| String::from_utf8_lossy(&self.body).into_owned())
+  }
+
+  /// The body parsed as JSON, such as the id Sentry responds with once it accepts an event.
+  pub fn json<T: DeserializeOwned>(&self) -> Result<T, BodyError> {
+    serde_json::from_str(&self.text()?).map_err(|err| BodyError::Json(err.to_string()))
+  }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+/// Why a response body couldn't be decoded.
+pub enum BodyError {
+  /// The body is in a charset that isn't understood.
+  UnsupportedCharset(String),
+  /// The body isn't valid text in its charset.
+  InvalidText(String),
+  /// The body isn't the JSON expected.
+  Json(String),
+}
+
+impl Error for BodyError {
+  fn description(&self) -> &str {
+    match *self {
+      BodyError::UnsupportedCharset(_) => "the body is in a charset that isn't understood",
+      BodyError::InvalidText(_) => "the body isn't valid text in its charset",
+      BodyError::Json(_) => "the body isn't the json expected",
+    }
+  }
+}
+
+impl fmt::Display for BodyError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      BodyError::UnsupportedCharset(ref charset) => write!(f, "The body's charset {} isn't understood", charset),
+      BodyError::InvalidText(ref charset) => write!(f, "The body isn't valid {}", charset),
+      BodyError::Json(ref message) => write!(f, "The body isn't the JSON expected: {}", message),
+    }
+  }
+}
+
 /// Future returned from `HttpResponse::buffer`.
 pub struct BufferedHttpResponseFuture {
   status: StatusCode,
@@ -90,12 +187,77 @@ impl HttpResponse {
   }
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+/// What went wrong while dispatching a request.
+pub enum DispatchErrorKind {
+  /// The host couldn't be resolved.
+  Dns,
+  /// The TLS handshake failed.
+  Tls,
+  /// No response arrived before the timeout.
+  Timeout,
+  /// The connection couldn't be made, or broke while the request was in flight.
+  Connection,
+  /// Anything else, such as an invalid request or response.
+  Other,
+}
+
+impl DispatchErrorKind {
+  /// Whether a request that failed this way might succeed if it's sent again, once the host can be
+  /// resolved and reached. A failed TLS handshake, such as with a certificate that isn't trusted, or
+  /// anything else, would fail the same way again.
+  pub fn is_retryable(&self) -> bool {
+    match *self {
+      DispatchErrorKind::Dns | DispatchErrorKind::Timeout | DispatchErrorKind::Connection => true,
+      DispatchErrorKind::Tls | DispatchErrorKind::Other => false,
+    }
+  }
+}
+
 #[derive(Debug, PartialEq)]
 /// An error produced when invalid request types are sent.
 pub struct HttpDispatchError {
+  kind: DispatchErrorKind,
   message: String,
 }
 
+impl HttpDispatchError {
+  /// Creates an error for `DispatchRequest` implementations outside this module.
+  pub fn new(kind: DispatchErrorKind, message: &str) -> HttpDispatchError {
+    HttpDispatchError {
+      kind: kind,
+      message: message.to_owned(),
+    }
+  }
+
+  /// What went wrong.
+  pub fn kind(&self) -> DispatchErrorKind {
+    self.kind
+  }
+
+  /// Whether the request might succeed if it's sent again, see `DispatchErrorKind::is_retryable`.
+  pub fn is_retryable(&self) -> bool {
+    self.kind.is_retryable()
+  }
+}
+
+/// Classifies an `io::Error` from connecting to or talking with a server.
+fn io_error_kind(err: &IoError) -> DispatchErrorKind {
+  match err.kind() {
+    IoErrorKind::TimedOut => DispatchErrorKind::Timeout,
+    IoErrorKind::ConnectionRefused
+    | IoErrorKind::ConnectionReset
+    | IoErrorKind::ConnectionAborted
+    | IoErrorKind::NotConnected
+    | IoErrorKind::AddrNotAvailable
+    | IoErrorKind::BrokenPipe
+    | IoErrorKind::UnexpectedEof => DispatchErrorKind::Connection,
+    // Resolving a host through the standard library fails with this message, whatever the platform.
+    _ if err.to_string().starts_with("failed to lookup address") => DispatchErrorKind::Dns,
+    _ => DispatchErrorKind::Other,
+  }
+}
+
 impl Error for HttpDispatchError {
   fn description(&self) -> &str {
     &self.message
@@ -110,8 +272,20 @@ impl fmt::Display for HttpDispatchError {
 
 impl From<HyperError> for HttpDispatchError {
   fn from(err: HyperError) -> HttpDispatchError {
-    HttpDispatchError {
-      message: err.description().to_string(),
+    match err {
+      HyperError::Io(io_err) => io_err.into(),
+      HyperError::Timeout => HttpDispatchError {
+        kind: DispatchErrorKind::Timeout,
+        message: err.to_string(),
+      },
+      HyperError::Incomplete | HyperError::Closed | HyperError::Cancel(_) => HttpDispatchError {
+        kind: DispatchErrorKind::Connection,
+        message: err.to_string(),
+      },
+      _ => HttpDispatchError {
+        kind: DispatchErrorKind::Other,
+        message: err.to_string(),
+      },
     }
   }
 }
@@ -119,7 +293,8 @@ impl From<HyperError> for HttpDispatchError {
 impl From<IoError> for HttpDispatchError {
   fn from(err: IoError) -> HttpDispatchError {
     HttpDispatchError {
-      message: err.description().to_string(),
+      kind: io_error_kind(&err),
+      message: err.to_string(),
     }
   }
 }
@@ -142,8 +317,106 @@ impl fmt::Display for TlsError {
   }
 }
 
+#[derive(Clone, Debug, Default, PartialEq)]
+/// Headers added to every request sent to Sentry, such as an auth header a proxy in front of it needs.
+pub struct RequestHeaders {
+  /// Sent as the `User-Agent` header. Defaults to `sentry-rs/<version>`.
+  pub user_agent: Option<String>,
+  /// Any other headers to send. These replace headers of the same name set by the client.
+  pub extra: Vec<(String, String)>,
+}
+
+impl RequestHeaders {
+  /// Creates a set of headers with the default `User-Agent`, and nothing extra.
+  pub fn new() -> RequestHeaders {
+    RequestHeaders::default()
+  }
+
+  /// Adds another header to send.
+  pub fn add(&mut self, name: &str, value: &str) {
+    self.extra.push((name.to_owned(), value.to_owned()));
+  }
+
+  /// Sets these headers on `headers`.
+  pub fn apply(&self, headers: &mut HyperHeaders) {
+    let user_agent = match self.user_agent {
+      Some(ref user_agent) => user_agent.clone(),
+      None => format!("sentry-rs/{}", env!("CARGO_PKG_VERSION")),
+    };
+    headers.set_raw("User-Agent", user_agent);
+    for &(ref name, ref value) in &self.extra {
+      headers.set_raw(name.clone(), value.clone());
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A request to Sentry as it's about to be sent, with its secrets redacted, see `Sentry::on_request`.
+pub struct OutgoingRequest {
+  /// Where the request is posted.
+  pub url: String,
+  /// The request's headers, in the order they're sent. Values of headers whose names look like they hold
+  /// secrets, such as `Authorization`, are `[redacted]`, and so are the keys in `X-Sentry-Auth`.
+  pub headers: Vec<(String, String)>,
+  /// The request's body, exactly as it's sent.
+  pub body: Vec<u8>,
+}
+
+impl OutgoingRequest {
+  /// Copies a request about to be posted to `url`, redacting its headers.
+  pub fn new(url: &str, headers: &HyperHeaders, body: &[u8]) -> OutgoingRequest {
+    let headers = headers
+      .iter()
+      .map(|header| {
+        let name = header.name().to_owned();
+        let value = redact_header(&name, header.value_string());
+        (name, value)
+      })
+      .collect();
+    OutgoingRequest {
+      url: url.to_owned(),
+      headers: headers,
+      body: body.to_vec(),
+    }
+  }
+
+  /// The value of the header called `name`, ignoring case, if it's sent.
+  pub fn header(&self, name: &str) -> Option<&str> {
+    self
+      .headers
+      .iter()
+      .find(|&&(ref header, _)| header.eq_ignore_ascii_case(name))
+      .map(|&(_, ref value)| value.as_str())
+  }
+}
+
+/// Redacts the value of the header called `name`, if it looks like a secret.
+fn redact_header(name: &str, value: String) -> String {
+  let lowercase = name.to_lowercase();
+  if lowercase == "x-sentry-auth" {
+    return value
+      .split(',')
+      .map(|field| match field.find('=') {
+        Some(index) if field[..index].ends_with("sentry_key") || field[..index].ends_with("sentry_secret") => {
+          format!("{}={}", &field[..index], REDACTED)
+        }
+        _ => field.to_owned(),
+      })
+      .collect::<Vec<_>>()
+      .join(",");
+  }
+  if is_redacted_header(name) {
+    return REDACTED.to_owned();
+  }
+  value
+}
+
 /// A future that will resolve to an `HttpResponse`.
-pub struct HttpClientFuture(ClientFutureInner);
+pub struct HttpClientFuture {
+  inner: ClientFutureInner,
+  /// Whether the request is made over TLS.
+  secure: bool,
+}
 
 enum ClientFutureInner {
   HyperWithTimeout(Select2<HyperFutureResponse, Timeout>),
@@ -155,16 +428,26 @@ impl Future for HttpClientFuture {
   type Error = HttpDispatchError;
 
   fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-    match self.0 {
+    let secure = self.secure;
+    match self.inner {
       ClientFutureInner::Error(ref message) => Err(HttpDispatchError {
+        kind: DispatchErrorKind::Other,
         message: message.clone(),
       }),
       ClientFutureInner::HyperWithTimeout(ref mut select_future) => match select_future.poll() {
+        // The TLS connector reports a failed handshake as an otherwise unremarkable I/O error.
+        Err(Either::A((HyperError::Io(ref io_err), _))) if secure && io_error_kind(io_err) == DispatchErrorKind::Other => {
+          Err(HttpDispatchError {
+            kind: DispatchErrorKind::Tls,
+            message: io_err.to_string(),
+          })
+        }
         Err(Either::A((hyper_err, _))) => Err(hyper_err.into()),
         Err(Either::B((io_err, _))) => Err(io_err.into()),
         Ok(Async::NotReady) => Ok(Async::NotReady),
         Ok(Async::Ready(Either::A((hyper_res, _)))) => Ok(Async::Ready(HttpResponse::from_hyper(hyper_res))),
         Ok(Async::Ready(Either::B(((), _)))) => Err(HttpDispatchError {
+          kind: DispatchErrorKind::Timeout,
           message: "Request timed out".into(),
         }),
       },
@@ -174,22 +457,69 @@ impl Future for HttpClientFuture {
 
 /// A Wrapper around hyper-client for tls connections.
 pub struct HttpsClient {
-  inner: HyperClient<HttpsConnector<HttpConnector>>,
+  inner: HyperClient<HttpsConnector<ResolvingConnector>>,
   handle: Handle,
 }
 
+#[derive(Clone)]
+/// How a client manages its connections.
+pub struct ClientOptions {
+  /// Whether connections are kept open to be reused by later requests. Defaults to `true`.
+  pub keep_alive: bool,
+  /// How long an idle connection is kept open for, or forever when `None`. Defaults to 90 seconds.
+  pub idle_timeout: Option<Duration>,
+  /// Looks up the addresses of hosts instead of the system resolver, when set.
+  pub resolver: Option<Arc<Resolve>>,
+  /// How many threads look up hosts with the system resolver, when there's no `resolver`. Defaults to
+  /// 1, since events rarely need more than one lookup at a time.
+  pub dns_threads: usize,
+}
+
+impl Default for ClientOptions {
+  fn default() -> ClientOptions {
+    ClientOptions {
+      keep_alive: true,
+      idle_timeout: Some(Duration::from_secs(90)),
+      resolver: None,
+      dns_threads: 1,
+    }
+  }
+}
+
+impl fmt::Debug for ClientOptions {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("ClientOptions")
+      .field("keep_alive", &self.keep_alive)
+      .field("idle_timeout", &self.idle_timeout)
+      .field("resolver", &self.resolver.as_ref().map(|_| "Resolve"))
+      .field("dns_threads", &self.dns_threads)
+      .finish()
+  }
+}
+
 impl HttpsClient {
   /// Create a tls-enabled http client.
   pub fn new(handle: &Handle) -> Result<HttpsClient, TlsError> {
-    let connector = match HttpsConnector::new(4, handle) {
-      Ok(connector) => connector,
+    HttpsClient::with_options(handle, ClientOptions::default())
+  }
+
+  /// Create a tls-enabled http client, managing its connections as `options` says.
+  pub fn with_options(handle: &Handle, options: ClientOptions) -> Result<HttpsClient, TlsError> {
+    let tls = match TlsConnector::builder().and_then(|builder| builder.build()) {
+      Ok(tls) => tls,
       Err(tls_error) => {
         return Err(TlsError {
           message: format!("Couldn't create NativeTlsClient: {}", tls_error),
         })
       }
     };
-    let inner = HyperClient::configure().connector(connector).build(handle);
+    let http = ResolvingConnector::new(options.resolver, options.dns_threads, false, handle);
+    let connector = HttpsConnector::from((http, tls));
+    let inner = HyperClient::configure()
+      .connector(connector)
+      .keep_alive(options.keep_alive)
+      .keep_alive_timeout(options.idle_timeout)
+      .build(handle);
     Ok(HttpsClient {
       inner: inner,
       handle: handle.clone(),
@@ -199,14 +529,23 @@ impl HttpsClient {
 
 /// A Wrapper around hyper-client for non-tls connections.
 pub struct HttpClient {
-  inner: HyperClient<HttpConnector>,
+  inner: HyperClient<ResolvingConnector>,
   handle: Handle,
 }
 
 impl HttpClient {
   /// Create a non-tls-enabled http client.
   pub fn new(handle: &Handle) -> Result<HttpClient, ()> {
-    let inner = HyperClient::configure().build(handle);
+    HttpClient::with_options(handle, ClientOptions::default())
+  }
+
+  /// Create a non-tls-enabled http client, managing its connections as `options` says.
+  pub fn with_options(handle: &Handle, options: ClientOptions) -> Result<HttpClient, ()> {
+    let inner = HyperClient::configure()
+      .connector(ResolvingConnector::new(options.resolver, options.dns_threads, true, handle))
+      .keep_alive(options.keep_alive)
+      .keep_alive_timeout(options.idle_timeout)
+      .build(handle);
     Ok(HttpClient {
       inner: inner,
       handle: handle.clone(),
@@ -234,7 +573,10 @@ impl DispatchRequest for HttpsClient {
       }
     };
 
-    HttpClientFuture(inner)
+    HttpClientFuture {
+      inner: inner,
+      secure: true,
+    }
   }
 }
 
@@ -250,6 +592,9 @@ impl DispatchRequest for HttpClient {
       }
     };
 
-    HttpClientFuture(inner)
+    HttpClientFuture {
+      inner: inner,
+      secure: false,
+    }
   }
 }
@@ -0,0 +1,154 @@
+//! Lets the address of Sentry's host be looked up some other way than the system resolver, such as
+//! pinning it to known addresses or asking an internal resolver.
+//!
+//! Set a resolver on `request::ClientOptions`, and the clients built with those options connect
+//! through a `ResolvingConnector` that asks it for addresses instead.
+
+use futures::{future, Future};
+use hyper::Uri;
+use hyper::client::HttpConnector;
+use tokio_core::net::TcpStream;
+use tokio_core::reactor::Handle;
+use tokio_service::Service;
+
+use std::cmp;
+use std::collections::HashMap;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult};
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
+
+/// Looks up the addresses of a host.
+///
+/// This is called on the event loop requests are sent from, so it should answer quickly, or requests
+/// to Sentry will wait on it.
+pub trait Resolve: Send + Sync {
+  /// The addresses `host` can be reached at on `port`, tried in order.
+  fn resolve(&self, host: &str, port: u16) -> IoResult<Vec<SocketAddr>>;
+}
+
+impl<F> Resolve for F
+where
+  F: Fn(&str, u16) -> IoResult<Vec<SocketAddr>> + Send + Sync,
+{
+  fn resolve(&self, host: &str, port: u16) -> IoResult<Vec<SocketAddr>> {
+    self(host, port)
+  }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+/// Resolves hosts to fixed addresses, and any others through the system resolver.
+///
+/// # Examples
+///
+/// ```rust
+/// use sentry_rs::resolve::{Resolve, StaticResolver};
+///
+/// let mut resolver = StaticResolver::new();
+/// resolver.add("sentry.internal", "10.0.0.5".parse().unwrap());
+/// let addresses = resolver.resolve("sentry.internal", 443).unwrap();
+/// assert_eq!(addresses, vec!["10.0.0.5:443".parse().unwrap()]);
+/// ```
+pub struct StaticResolver {
+  /// The addresses of each host, tried in order.
+  pub addresses: HashMap<String, Vec<IpAddr>>,
+}
+
+impl StaticResolver {
+  /// Creates a resolver with no fixed addresses.
+  pub fn new() -> StaticResolver {
+    StaticResolver::default()
+  }
+
+  /// Adds `address` to the addresses `host` resolves to.
+  pub fn add(&mut self, host: &str, address: IpAddr) {
+    self.addresses.entry(host.to_owned()).or_insert_with(Vec::new).push(address);
+  }
+}
+
+impl Resolve for StaticResolver {
+  fn resolve(&self, host: &str, port: u16) -> IoResult<Vec<SocketAddr>> {
+    match self.addresses.get(host) {
+      Some(addresses) => Ok(addresses.iter().map(|ip| SocketAddr::new(*ip, port)).collect()),
+      None => (host, port).to_socket_addrs().map(|addresses| addresses.collect()),
+    }
+  }
+}
+
+/// Connects to hosts looked up with a `Resolve`, or with hyper's own connector when there isn't one.
+pub struct ResolvingConnector {
+  lookup: Lookup,
+  handle: Handle,
+}
+
+/// How a `ResolvingConnector` finds a host's addresses.
+enum Lookup {
+  /// With hyper's own connector, which starts threads to use the system resolver on.
+  System(HttpConnector),
+  /// With a resolver.
+  Resolver(Arc<Resolve>),
+}
+
+impl ResolvingConnector {
+  /// Creates a connector that looks hosts up with `resolver`, or the system resolver on `dns_threads`
+  /// threads when `None`. `enforce_http` refuses URIs that aren't `http`, and should be off when TLS is
+  /// layered on top.
+  pub fn new(
+    resolver: Option<Arc<Resolve>>,
+    dns_threads: usize,
+    enforce_http: bool,
+    handle: &Handle,
+  ) -> ResolvingConnector {
+    let lookup = match resolver {
+      Some(resolver) => Lookup::Resolver(resolver),
+      None => {
+        let mut http = HttpConnector::new(cmp::max(dns_threads, 1), handle);
+        http.enforce_http(enforce_http);
+        Lookup::System(http)
+      }
+    };
+    ResolvingConnector {
+      lookup: lookup,
+      handle: handle.clone(),
+    }
+  }
+}
+
+impl Service for ResolvingConnector {
+  type Request = Uri;
+  type Response = TcpStream;
+  type Error = IoError;
+  type Future = Box<Future<Item = TcpStream, Error = IoError>>;
+
+  fn call(&self, uri: Uri) -> Self::Future {
+    let resolver = match self.lookup {
+      Lookup::Resolver(ref resolver) => resolver,
+      Lookup::System(ref http) => return Box::new(http.call(uri)),
+    };
+    let host = match uri.host() {
+      Some(host) => host,
+      None => return Box::new(future::err(IoError::new(IoErrorKind::InvalidInput, "invalid url, missing host"))),
+    };
+    let port = uri.port().unwrap_or_else(|| if uri.scheme() == Some("https") { 443 } else { 80 });
+    // Failures are described the way the standard library describes them, so they're still told
+    // apart as DNS failures.
+    let addresses = match resolver.resolve(host, port) {
+      Ok(addresses) => addresses,
+      Err(err) => {
+        return Box::new(future::err(IoError::new(
+          err.kind(),
+          format!("failed to lookup address information: {}", err),
+        )))
+      }
+    };
+
+    let mut connecting: Self::Future = Box::new(future::err(IoError::new(
+      IoErrorKind::Other,
+      format!("failed to lookup address information: no addresses for {}", host),
+    )));
+    for address in addresses {
+      let handle = self.handle.clone();
+      connecting = Box::new(connecting.or_else(move |_| TcpStream::connect(&address, &handle)));
+    }
+    connecting
+  }
+}
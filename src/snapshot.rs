@@ -0,0 +1,162 @@
+//! Snapshots of how the process was run, sent as extras with panic events, see
+//! `Sentry::set_panic_snapshot`. Debugging a crashed command-line tool usually starts with what it was run
+//! with, which is otherwise lost with the process.
+//!
+//! Nothing is taken unless asked for. Values that look like secrets are replaced with `[redacted]`: those
+//! of environment variables whose names contain one of the `redact` names, and of arguments given as
+//! `--name=value` or `--name value` where the name does.
+
+use models::Event;
+use serde_json::Value;
+
+use std::collections::HashMap;
+use std::env;
+
+/// What redacted values are replaced with.
+pub const REDACTED: &'static str = "[redacted]";
+
+/// The names redacted by default, matched without regard to case.
+pub const DEFAULT_REDACT: &'static [&'static str] = &["auth", "credential", "key", "password", "secret", "token"];
+
+/// Whether the value of the HTTP header or query parameter called `name` is redacted: `Cookie`, and those
+/// whose names contain one of the `DEFAULT_REDACT` names, ignoring case.
+///
+/// # Examples
+///
+/// ```rust
+/// use sentry_rs::snapshot::is_redacted_header;
+///
+/// assert!(is_redacted_header("Proxy-Authorization"));
+/// assert!(is_redacted_header("Cookie"));
+/// assert!(!is_redacted_header("User-Agent"));
+/// ```
+pub fn is_redacted_header(name: &str) -> bool {
+  let name = name.to_lowercase();
+  name == "cookie" || DEFAULT_REDACT.iter().any(|redacted| name.contains(redacted))
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+/// Which parts of how the process was run are sent: extras named `env`, `cwd`, and `args`.
+///
+/// # Examples
+///
+/// ```rust
+/// use sentry_rs::snapshot::ProcessSnapshot;
+///
+/// let mut snapshot = ProcessSnapshot::new();
+/// snapshot.args = true;
+/// snapshot.redact.push("dsn".to_owned());
+///
+/// let args = vec!["deploy", "--api-token", "hunter2", "--dsn=https://key@sentry.io/1", "prod"];
+/// assert_eq!(
+///   snapshot.redact_args(args.into_iter().map(String::from)),
+///   vec!["deploy", "--api-token", "[redacted]", "--dsn=[redacted]", "prod"]
+/// );
+/// ```
+pub struct ProcessSnapshot {
+  /// The environment variables sent, by name. Ones that aren't set are left out.
+  pub env_vars: Vec<String>,
+  /// Whether the working directory is sent.
+  pub cwd: bool,
+  /// Whether the command-line arguments are sent, the program's own path first.
+  pub args: bool,
+  /// Environment variables and arguments whose names contain one of these, ignoring case, have their
+  /// values redacted. Defaults to `DEFAULT_REDACT`.
+  pub redact: Vec<String>,
+}
+
+impl Default for ProcessSnapshot {
+  fn default() -> ProcessSnapshot {
+    ProcessSnapshot {
+      env_vars: Vec::new(),
+      cwd: false,
+      args: false,
+      redact: DEFAULT_REDACT.iter().map(|name| (*name).to_owned()).collect(),
+    }
+  }
+}
+
+impl ProcessSnapshot {
+  /// Creates a snapshot that takes nothing, redacting the `DEFAULT_REDACT` names.
+  pub fn new() -> ProcessSnapshot {
+    ProcessSnapshot::default()
+  }
+
+  /// Whether the value of a variable or argument called `name` is redacted.
+  pub fn is_redacted(&self, name: &str) -> bool {
+    let name = name.to_lowercase();
+    self.redact.iter().any(|redacted| name.contains(&redacted.to_lowercase()))
+  }
+
+  /// Redacts the values of `args` that look like secrets, given as `--name=value`, or as `--name` followed
+  /// by the value.
+  pub fn redact_args<I: IntoIterator<Item = String>>(&self, args: I) -> Vec<String> {
+    let mut redact_next = false;
+    args
+      .into_iter()
+      .map(|arg| {
+        if redact_next && !arg.starts_with('-') {
+          redact_next = false;
+          return REDACTED.to_owned();
+        }
+        redact_next = false;
+        if !arg.starts_with('-') {
+          return arg;
+        }
+        let (name, value) = match arg.find('=') {
+          Some(index) => (&arg[..index], Some(&arg[index + 1..])),
+          None => (&arg[..], None),
+        };
+        if !self.is_redacted(name.trim_start_matches('-')) {
+          return arg;
+        }
+        match value {
+          Some(_) => format!("{}={}", name, REDACTED),
+          None => {
+            redact_next = true;
+            arg
+          }
+        }
+      })
+      .collect()
+  }
+
+  /// Takes the snapshot, as the extras it's sent as.
+  pub fn take(&self) -> HashMap<String, Value> {
+    let mut extra = HashMap::new();
+    if !self.env_vars.is_empty() {
+      let vars = self
+        .env_vars
+        .iter()
+        .filter_map(|name| env::var_os(name).map(|value| (name, value)))
+        .map(|(name, value)| {
+          let value = if self.is_redacted(name) {
+            REDACTED.to_owned()
+          } else {
+            value.to_string_lossy().into_owned()
+          };
+          (name.clone(), Value::String(value))
+        })
+        .collect();
+      extra.insert("env".to_owned(), Value::Object(vars));
+    }
+    if self.cwd {
+      if let Ok(cwd) = env::current_dir() {
+        extra.insert("cwd".to_owned(), Value::String(cwd.to_string_lossy().into_owned()));
+      }
+    }
+    if self.args {
+      let args = self.redact_args(env::args_os().map(|arg| arg.to_string_lossy().into_owned()));
+      extra.insert("args".to_owned(), Value::Array(args.into_iter().map(Value::String).collect()));
+    }
+    extra
+  }
+
+  /// Adds the snapshot to `event`'s extras, unless it already has extras with the same keys.
+  pub fn apply(&self, event: &mut Event) {
+    for (key, value) in self.take() {
+      event.extra.entry(key).or_insert(value);
+    }
+  }
+}
@@ -0,0 +1,155 @@
+//! Captures the current thread's stack as frames for an event, the same way the panic handler does, so
+//! events built by hand or by other integrations get stack traces that look like, and are grouped like,
+//! the ones sent for panics.
+
+use StacktraceMode;
+use models::StackFrame;
+
+use backtrace;
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+/// How many lines of source are read on each side of a frame's line.
+pub const CONTEXT_LINES: usize = 5;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+/// How `capture_stacktrace` turns the stack into frames.
+pub struct StacktraceOptions {
+  /// Whether frames are resolved in the process, or only given their addresses.
+  pub mode: StacktraceMode,
+  /// Whether the lines around each frame's line are read from its source file, when it can be found.
+  /// Defaults to whether the `sourcemap` feature is on.
+  pub source_context: bool,
+}
+
+impl Default for StacktraceOptions {
+  fn default() -> StacktraceOptions {
+    StacktraceOptions::new(StacktraceMode::default())
+  }
+}
+
+impl StacktraceOptions {
+  /// Options capturing frames with `mode`, with source context when the `sourcemap` feature is on.
+  pub fn new(mode: StacktraceMode) -> StacktraceOptions {
+    StacktraceOptions {
+      mode: mode,
+      source_context: cfg!(feature = "sourcemap"),
+    }
+  }
+}
+
+/// Captures the stack of the current thread, from whoever called this down to where the thread started,
+/// skipping the `skip_frames` frames nearest the call as well, such as a helper the call is made from.
+/// With `StacktraceMode::RawAddresses` frames only get their addresses, and nothing is resolved.
+///
+/// # Examples
+///
+/// ```rust
+/// use sentry_rs::models::Event;
+/// use sentry_rs::stacktrace::{self, StacktraceOptions};
+///
+/// let mut event = Event::new("app", "error", "Retries exhausted", None, None, None, None, None, None, None);
+/// event.stacktrace = Some(stacktrace::capture_stacktrace(0, &StacktraceOptions::default()));
+/// assert!(!event.stacktrace.unwrap().is_empty());
+/// ```
+#[inline(never)]
+pub fn capture_stacktrace(skip_frames: usize, options: &StacktraceOptions) -> Vec<StackFrame> {
+  let this = capture_stacktrace as *const () as usize;
+  let mut addresses = vec![];
+  backtrace::trace(|frame: &backtrace::Frame| {
+    addresses.push((frame.ip(), frame.symbol_address()));
+    true
+  });
+  // The frames of the trace itself come first, up to this function's own, which are left out. Some
+  // platforms can't tell where a frame's function starts, and then none are.
+  let ours = addresses
+    .iter()
+    .position(|&(_, symbol_address)| symbol_address as usize == this)
+    .map_or(0, |index| index + 1);
+
+  let mut frames = vec![];
+  for &(ip, symbol_address) in addresses.iter().skip(ours + skip_frames) {
+    if options.mode == StacktraceMode::RawAddresses {
+      frames.push(StackFrame {
+        filename: String::new(),
+        function: Cow::Borrowed("unresolved symbol"),
+        lineno: 0,
+        pre_context: Vec::new(),
+        post_context: Vec::new(),
+        context_line: String::new(),
+        in_app: false,
+        colno: None,
+        abs_path: None,
+        module: None,
+        instruction_addr: Some(format!("{:#x}", ip as usize)),
+        symbol_addr: None,
+        vars: HashMap::new(),
+      });
+      continue;
+    }
+    backtrace::resolve(ip, |symbol| {
+      let name = symbol
+        .name()
+        .map_or(Cow::Borrowed("unresolved symbol"), |name| Cow::Owned(name.to_string()));
+      let filename = symbol
+        .filename()
+        .map_or("".to_string(), |sym| format!("{:?}", sym));
+      let lineno = symbol.lineno().unwrap_or(0);
+
+      let mut pre_context = Vec::new();
+      let mut context_line = String::new();
+      let mut post_context = Vec::new();
+      let fixed_filename = filename.replace("\"", "");
+
+      if options.source_context && lineno > 0 {
+        let f = File::open(&fixed_filename);
+        if f.is_ok() {
+          let file = f.unwrap();
+          let buffed_reader = BufReader::new(&file);
+          let first = (lineno as usize).saturating_sub(CONTEXT_LINES + 1);
+          let items = buffed_reader.lines().skip(first).take(CONTEXT_LINES * 2 + 1);
+
+          // Lines before the frame's own are its pre context, though there are fewer of them near the
+          // top of the file.
+          let pivot = lineno as usize - 1 - first;
+          for (idx, val) in items.enumerate() {
+            if let Ok(true_item) = val {
+              if idx < pivot {
+                pre_context.push(true_item);
+              } else if idx == pivot {
+                context_line = true_item;
+              } else {
+                post_context.push(true_item);
+              }
+            }
+          }
+        } else {
+          drop(f);
+        }
+      }
+
+      let in_app = !(fixed_filename.starts_with("/buildslave") || fixed_filename == ""
+        || fixed_filename.starts_with("/checkout"));
+
+      frames.push(StackFrame {
+        filename: filename,
+        function: name,
+        lineno: lineno,
+        pre_context: pre_context,
+        post_context: post_context,
+        context_line: context_line,
+        in_app: in_app,
+        colno: symbol.colno(),
+        abs_path: if fixed_filename.is_empty() { None } else { Some(fixed_filename) },
+        module: None,
+        instruction_addr: Some(format!("{:#x}", ip as usize)),
+        symbol_addr: Some(format!("{:#x}", symbol_address as usize)),
+        vars: HashMap::new(),
+      });
+    });
+  }
+  frames
+}
@@ -0,0 +1,204 @@
+//! Counters for how events are moving through a Sentry client, so you can tell when the SDK itself is
+//! backing up or failing to deliver.
+//!
+//! When events are sent to more than one project, each destination is also counted on its own, so a
+//! project that keeps failing can be told apart from the rest.
+//!
+//! `Sentry::healthy` sums these up into whether error reporting is working at all, for a health check
+//! endpoint to expose.
+
+use MAX_QUEUED_EVENTS;
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// How many sends in a row have to fail before a client is unhealthy.
+pub const UNHEALTHY_FAILURES: usize = 5;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+/// A snapshot of a clients delivery counters, from `Sentry::stats`.
+pub struct Stats {
+  /// Events handed to the worker queue.
+  pub queued: usize,
+  /// Events currently being sent.
+  pub in_flight: usize,
+  /// Events Sentry accepted.
+  pub sent: usize,
+  /// Events that failed to reach Sentry.
+  pub failed: usize,
+  /// Events that couldn't be queued at all, such as when the worker has gone away.
+  pub dropped: usize,
+  /// Events waiting in the worker queue to be sent.
+  pub queue_depth: usize,
+  /// How many times the worker has restarted after panicking.
+  pub worker_restarts: usize,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+/// Whether a client is able to report errors, from `Sentry::healthy`.
+pub struct HealthStatus {
+  /// Whether the client still takes events, that is it hasn't been closed.
+  pub open: bool,
+  /// Whether the worker thread is running. It's only spawned once there's an event to send, and may
+  /// exit when idle, so this is only a problem while events are waiting.
+  pub worker_alive: bool,
+  /// Events waiting in the worker queue to be sent.
+  pub queue_depth: usize,
+  /// How long ago Sentry last accepted an event, `None` if it never has.
+  pub since_last_sent: Option<Duration>,
+  /// How long ago an event last failed to reach Sentry, `None` if none ever has.
+  pub since_last_failed: Option<Duration>,
+  /// How many sends in a row have failed, since Sentry last accepted an event.
+  pub consecutive_failures: usize,
+  /// How many bytes of events are waiting in the dead letter file to be resubmitted, 0 without one.
+  pub dead_letter_bytes: u64,
+}
+
+impl HealthStatus {
+  /// Whether errors are being reported: the client is open, fewer than `UNHEALTHY_FAILURES` sends in a
+  /// row have failed, and the queue is neither full nor waiting on a worker that isn't running.
+  pub fn is_healthy(&self) -> bool {
+    self.open && self.consecutive_failures < UNHEALTHY_FAILURES && self.queue_depth < MAX_QUEUED_EVENTS
+      && (self.worker_alive || self.queue_depth == 0)
+  }
+}
+
+#[derive(Debug, Default)]
+/// The live counters behind `Stats`, updated as events move through the client.
+pub struct StatsCounters {
+  queued: AtomicUsize,
+  dequeued: AtomicUsize,
+  in_flight: AtomicUsize,
+  sent: AtomicUsize,
+  failed: AtomicUsize,
+  dropped: AtomicUsize,
+  consecutive_failures: AtomicUsize,
+  last_sent: Mutex<Option<Instant>>,
+  last_failed: Mutex<Option<Instant>>,
+}
+
+impl StatsCounters {
+  /// Counts an event handed to the worker queue.
+  pub fn queued(&self) {
+    self.queued.fetch_add(1, Ordering::Relaxed);
+  }
+
+  /// Counts an event the worker has taken off its queue.
+  pub fn dequeued(&self) {
+    self.dequeued.fetch_add(1, Ordering::Relaxed);
+  }
+
+  /// Counts an event that couldn't be queued.
+  pub fn dropped(&self) {
+    self.dropped.fetch_add(1, Ordering::Relaxed);
+  }
+
+  /// Counts an event that's started sending.
+  pub fn send_started(&self) {
+    self.in_flight.fetch_add(1, Ordering::Relaxed);
+  }
+
+  /// Counts an event that's finished sending, successfully or not.
+  pub fn send_finished(&self, success: bool) {
+    self.in_flight.fetch_sub(1, Ordering::Relaxed);
+    let last = if success {
+      self.sent.fetch_add(1, Ordering::Relaxed);
+      self.consecutive_failures.store(0, Ordering::Relaxed);
+      &self.last_sent
+    } else {
+      self.failed.fetch_add(1, Ordering::Relaxed);
+      self.consecutive_failures.fetch_add(1, Ordering::Relaxed);
+      &self.last_failed
+    };
+    let mut last = match last.lock() {
+      Ok(guard) => guard,
+      Err(poisoned) => poisoned.into_inner(),
+    };
+    *last = Some(Instant::now());
+  }
+
+  /// Takes a snapshot of the counters. `worker_restarts` is kept by the worker itself, so it's left at 0.
+  pub fn snapshot(&self) -> Stats {
+    let queued = self.queued.load(Ordering::Relaxed);
+    Stats {
+      queued: queued,
+      in_flight: self.in_flight.load(Ordering::Relaxed),
+      sent: self.sent.load(Ordering::Relaxed),
+      failed: self.failed.load(Ordering::Relaxed),
+      dropped: self.dropped.load(Ordering::Relaxed),
+      queue_depth: queued.saturating_sub(self.dequeued.load(Ordering::Relaxed)),
+      worker_restarts: 0,
+    }
+  }
+
+  /// Takes a snapshot of the counters that tell whether sending works. The worker and dead letter file are
+  /// kept by the client, so `open` and `worker_alive` are left `true`, and `dead_letter_bytes` at 0.
+  pub fn health(&self) -> HealthStatus {
+    let since = |last: &Mutex<Option<Instant>>| {
+      let last = match last.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+      };
+      last.map(|at| at.elapsed())
+    };
+    HealthStatus {
+      open: true,
+      worker_alive: true,
+      queue_depth: self.snapshot().queue_depth,
+      since_last_sent: since(&self.last_sent),
+      since_last_failed: since(&self.last_failed),
+      consecutive_failures: self.consecutive_failures.load(Ordering::Relaxed),
+      dead_letter_bytes: 0,
+    }
+  }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+/// How events have fared at one destination, from `Sentry::destination_stats`.
+pub struct DestinationStats {
+  /// The destination, as given by `SentryCredentials::destination`.
+  pub destination: String,
+  /// Events this destination accepted.
+  pub sent: usize,
+  /// Events that failed to reach this destination.
+  pub failed: usize,
+}
+
+#[derive(Debug, Default)]
+/// The live counters behind `DestinationStats`, for every destination events have been sent to.
+pub struct DestinationCounters {
+  destinations: Mutex<BTreeMap<String, DestinationStats>>,
+}
+
+impl DestinationCounters {
+  /// Counts an event that's finished sending to `destination`, successfully or not.
+  pub fn record(&self, destination: &str, success: bool) {
+    let mut destinations = match self.destinations.lock() {
+      Ok(guard) => guard,
+      Err(poisoned) => poisoned.into_inner(),
+    };
+    let stats = destinations
+      .entry(destination.to_owned())
+      .or_insert_with(|| DestinationStats {
+        destination: destination.to_owned(),
+        sent: 0,
+        failed: 0,
+      });
+    if success {
+      stats.sent += 1;
+    } else {
+      stats.failed += 1;
+    }
+  }
+
+  /// Takes a snapshot of every destination's counters, in order of their names.
+  pub fn snapshot(&self) -> Vec<DestinationStats> {
+    let destinations = match self.destinations.lock() {
+      Ok(guard) => guard,
+      Err(poisoned) => poisoned.into_inner(),
+    };
+    destinations.values().cloned().collect()
+  }
+}
@@ -0,0 +1,341 @@
+//! Checks events against the limits Sentry documents, so a payload it would reject or cut short is
+//! caught when it's built rather than noticed missing later.
+//!
+//! `Event::validate` lists what's wrong with an event. `Sentry::set_validate_events` logs the issues of
+//! every event before it's sent, and is on by default in debug builds. `trim_value` cuts structured extra
+//! data down to what Sentry keeps, the way it would.
+//!
+//! Tags are the exception: Sentry drops a whole event over a single invalid tag, so `sanitize_tags` fixes
+//! them up before every event is sent, rather than only warning about them.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde_json::Value;
+
+use MAX_BREADCRUMBS;
+use models::{Environment, EnvironmentError, Event, Level};
+
+/// The most characters of a message Sentry keeps.
+pub const MAX_MESSAGE_LENGTH: usize = 8192;
+/// The most characters of a logger name Sentry accepts.
+pub const MAX_LOGGER_LENGTH: usize = 64;
+/// The most characters of a culprit or transaction Sentry accepts.
+pub const MAX_CULPRIT_LENGTH: usize = 200;
+/// The most characters of a release Sentry accepts.
+pub const MAX_RELEASE_LENGTH: usize = 200;
+/// The most characters of a distribution Sentry accepts.
+pub const MAX_DIST_LENGTH: usize = 64;
+/// The most characters of an environment Sentry accepts.
+pub const MAX_ENVIRONMENT_LENGTH: usize = 64;
+/// The most characters of a tag key Sentry accepts.
+pub const MAX_TAG_KEY_LENGTH: usize = 32;
+/// The most characters of a tag value Sentry accepts.
+pub const MAX_TAG_VALUE_LENGTH: usize = 200;
+/// The most tags Sentry keeps on an event.
+pub const MAX_TAGS: usize = 50;
+/// The most stack frames Sentry keeps on an event.
+pub const MAX_FRAMES: usize = 250;
+/// How deep Sentry keeps structured extra data. Anything deeper is replaced with `[Object]` or `[Array]`.
+pub const MAX_EXTRA_DEPTH: usize = 5;
+/// The most entries of an object, or items of an array, Sentry keeps in structured extra data.
+pub const MAX_EXTRA_ITEMS: usize = 50;
+/// The most characters of a string Sentry keeps in structured extra data.
+pub const MAX_EXTRA_STRING_LENGTH: usize = 512;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+/// Something about an event Sentry would reject or cut short.
+pub enum ValidationIssue {
+  /// The event id isn't 32 hex characters.
+  InvalidEventId(String),
+  /// The level isn't one Sentry understands.
+  InvalidLevel(String),
+  /// The environment isn't a valid `Environment` as it is, such as having whitespace around it.
+  InvalidEnvironment(String, EnvironmentError),
+  /// A field is longer than Sentry allows. A tag key that's too long is reported as `tags`, and a tag
+  /// value as `tags.<key>`.
+  TooLong {
+    field: String,
+    length: usize,
+    max: usize,
+  },
+  /// The event has more tags than Sentry keeps.
+  TooManyTags { count: usize, max: usize },
+  /// The stack trace has more frames than Sentry keeps.
+  TooManyFrames { count: usize, max: usize },
+  /// The event has more breadcrumbs than Sentry keeps.
+  TooManyBreadcrumbs { count: usize, max: usize },
+}
+
+impl fmt::Display for ValidationIssue {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      ValidationIssue::InvalidEventId(ref id) => write!(f, "event id {:?} isn't 32 hex characters", id),
+      ValidationIssue::InvalidLevel(ref level) => write!(f, "level {:?} isn't one Sentry understands", level),
+      ValidationIssue::InvalidEnvironment(ref environment, ref err) => {
+        write!(f, "environment {:?} is invalid: {}", environment, err)
+      }
+      ValidationIssue::TooLong {
+        ref field,
+        length,
+        max,
+      } => write!(f, "{} is {} characters long, more than {}", field, length, max),
+      ValidationIssue::TooManyTags { count, max } => write!(f, "{} tags, more than {}", count, max),
+      ValidationIssue::TooManyFrames { count, max } => write!(f, "{} stack frames, more than {}", count, max),
+      ValidationIssue::TooManyBreadcrumbs { count, max } => write!(f, "{} breadcrumbs, more than {}", count, max),
+    }
+  }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+/// A tag `sanitize_tags` changed or dropped so Sentry would accept the event it's on.
+pub enum TagCoercion {
+  /// A key had characters Sentry doesn't allow, which were replaced with `_`, or was longer than
+  /// `MAX_TAG_KEY_LENGTH` and was cut short.
+  Key { from: String, to: String },
+  /// The value of the tag with `key` had newlines, which were replaced with spaces, or was longer than
+  /// `MAX_TAG_VALUE_LENGTH` and was cut short.
+  Value { key: String, from: String, to: String },
+  /// A tag was dropped, because its key or value was empty, or its key was the same as another tag's once
+  /// sanitized.
+  Dropped { key: String, value: String },
+}
+
+impl fmt::Display for TagCoercion {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      TagCoercion::Key { ref from, ref to } => write!(f, "tag key {:?} was changed to {:?}", from, to),
+      TagCoercion::Value {
+        ref key,
+        ref from,
+        ref to,
+      } => write!(f, "value {:?} of tag {:?} was changed to {:?}", from, key, to),
+      TagCoercion::Dropped { ref key, ref value } => write!(f, "tag {:?} with value {:?} was dropped", key, value),
+    }
+  }
+}
+
+/// `key` as Sentry accepts it: only ASCII letters, digits, and `_.:-`, with anything else replaced with
+/// `_`, and at most `MAX_TAG_KEY_LENGTH` characters.
+///
+/// # Examples
+///
+/// ```rust
+/// use sentry_rs::validate::sanitize_tag_key;
+///
+/// assert_eq!(sanitize_tag_key("http.status_code"), "http.status_code");
+/// assert_eq!(sanitize_tag_key("user id"), "user_id");
+/// ```
+pub fn sanitize_tag_key(key: &str) -> String {
+  key
+    .chars()
+    .take(MAX_TAG_KEY_LENGTH)
+    .map(|c| if c.is_ascii_alphanumeric() || "_.:-".contains(c) { c } else { '_' })
+    .collect()
+}
+
+/// `value` as Sentry accepts it: with newlines replaced with spaces, and at most `MAX_TAG_VALUE_LENGTH`
+/// characters.
+pub fn sanitize_tag_value(value: &str) -> String {
+  value
+    .chars()
+    .take(MAX_TAG_VALUE_LENGTH)
+    .map(|c| if c == '\n' || c == '\r' { ' ' } else { c })
+    .collect()
+}
+
+/// Changes `tags` in place so Sentry accepts every one of them, see `sanitize_tag_key` and
+/// `sanitize_tag_value`, returning what was changed. Tags left with an empty key or value are dropped, and
+/// so is a tag whose sanitized key is taken, keeping the tag that had it first, or else the first key in
+/// sorted order.
+///
+/// # Examples
+///
+/// ```rust
+/// use sentry_rs::validate::{sanitize_tags, TagCoercion};
+/// use std::collections::HashMap;
+///
+/// let mut tags = HashMap::new();
+/// tags.insert("user id".to_owned(), "42".to_owned());
+/// let coercions = sanitize_tags(&mut tags);
+/// assert_eq!(tags["user_id"], "42");
+/// assert_eq!(
+///   coercions,
+///   vec![TagCoercion::Key { from: "user id".to_owned(), to: "user_id".to_owned() }]
+/// );
+/// ```
+pub fn sanitize_tags(tags: &mut HashMap<String, String>) -> Vec<TagCoercion> {
+  let mut coercions = Vec::new();
+  let mut invalid = tags
+    .iter()
+    .filter(|&(key, value)| {
+      key.is_empty() || value.is_empty() || sanitize_tag_key(key) != *key || sanitize_tag_value(value) != *value
+    })
+    .map(|(key, _)| key.clone())
+    .collect::<Vec<String>>();
+  invalid.sort();
+  let invalid = invalid
+    .into_iter()
+    .filter_map(|key| tags.remove_entry(&key))
+    .collect::<Vec<(String, String)>>();
+  for (key, value) in invalid {
+    let sanitized_key = sanitize_tag_key(&key);
+    let sanitized_value = sanitize_tag_value(&value);
+    if sanitized_key.is_empty() || sanitized_value.is_empty() || tags.contains_key(&sanitized_key) {
+      coercions.push(TagCoercion::Dropped {
+        key: key,
+        value: value,
+      });
+      continue;
+    }
+    if sanitized_key != key {
+      coercions.push(TagCoercion::Key {
+        from: key.clone(),
+        to: sanitized_key.clone(),
+      });
+    }
+    if sanitized_value != value {
+      coercions.push(TagCoercion::Value {
+        key: sanitized_key.clone(),
+        from: value,
+        to: sanitized_value.clone(),
+      });
+    }
+    tags.insert(sanitized_key, sanitized_value);
+  }
+  coercions
+}
+
+/// Lists what's wrong with `event`, in the order its fields are checked.
+pub fn validate(event: &Event) -> Vec<ValidationIssue> {
+  let mut issues = Vec::new();
+  if event.event_id.len() != 32 || !event.event_id.chars().all(|c| c.is_digit(16)) {
+    issues.push(ValidationIssue::InvalidEventId(event.event_id.clone()));
+  }
+  if Level::from_name(&event.level).is_none() {
+    issues.push(ValidationIssue::InvalidLevel(event.level.to_string()));
+  }
+
+  check_length(&mut issues, "message", &event.message, MAX_MESSAGE_LENGTH);
+  check_length(&mut issues, "logger", &event.logger, MAX_LOGGER_LENGTH);
+  let optional = [
+    ("culprit", &event.culprit, MAX_CULPRIT_LENGTH),
+    ("transaction", &event.transaction, MAX_CULPRIT_LENGTH),
+    ("release", &event.release, MAX_RELEASE_LENGTH),
+    ("dist", &event.dist, MAX_DIST_LENGTH),
+  ];
+  for &(field, value, max) in optional.iter() {
+    if let Some(ref value) = *value {
+      check_length(&mut issues, field, value, max);
+    }
+  }
+
+  if let Some(ref environment) = event.environment {
+    match Environment::new(environment) {
+      Ok(ref valid) if valid.as_str() == environment => {}
+      Ok(_) => {
+        // Only the whitespace around it was trimmed.
+        let space = environment.chars().find(|c| c.is_whitespace()).unwrap_or(' ');
+        let err = EnvironmentError::InvalidCharacter(space);
+        issues.push(ValidationIssue::InvalidEnvironment(environment.clone(), err));
+      }
+      Err(err) => issues.push(ValidationIssue::InvalidEnvironment(environment.clone(), err)),
+    }
+  }
+
+  if event.tags.len() > MAX_TAGS {
+    issues.push(ValidationIssue::TooManyTags {
+      count: event.tags.len(),
+      max: MAX_TAGS,
+    });
+  }
+  let mut keys = event.tags.keys().collect::<Vec<&String>>();
+  keys.sort();
+  for key in keys {
+    check_length(&mut issues, "tags", key, MAX_TAG_KEY_LENGTH);
+    check_length(&mut issues, &format!("tags.{}", key), &event.tags[key], MAX_TAG_VALUE_LENGTH);
+  }
+
+  if let Some(ref frames) = event.stacktrace {
+    if frames.len() > MAX_FRAMES {
+      issues.push(ValidationIssue::TooManyFrames {
+        count: frames.len(),
+        max: MAX_FRAMES,
+      });
+    }
+  }
+  if event.breadcrumbs.len() > MAX_BREADCRUMBS {
+    issues.push(ValidationIssue::TooManyBreadcrumbs {
+      count: event.breadcrumbs.len(),
+      max: MAX_BREADCRUMBS,
+    });
+  }
+  issues
+}
+
+/// Adds an issue when `value` is longer than `max` characters.
+fn check_length(issues: &mut Vec<ValidationIssue>, field: &str, value: &str, max: usize) {
+  let length = value.chars().count();
+  if length > max {
+    issues.push(ValidationIssue::TooLong {
+      field: field.to_owned(),
+      length: length,
+      max: max,
+    });
+  }
+}
+
+/// Cuts `value` down to what Sentry keeps of extra data: objects and arrays nested deeper than
+/// `MAX_EXTRA_DEPTH` are replaced with `[Object]` or `[Array]`, only their first `MAX_EXTRA_ITEMS`
+/// entries are kept, and strings are cut to `MAX_EXTRA_STRING_LENGTH` characters, ending in `...`.
+///
+/// # Examples
+///
+/// ```rust
+/// #[macro_use]
+/// extern crate serde_json;
+/// extern crate sentry_rs;
+///
+/// use sentry_rs::validate::trim_value;
+///
+/// fn main() {
+///   let mut value = json!({"a": {"b": {"c": {"d": {"e": {"f": 1}}}}}});
+///   trim_value(&mut value);
+///   assert_eq!(value, json!({"a": {"b": {"c": {"d": {"e": "[Object]"}}}}}));
+/// }
+/// ```
+pub fn trim_value(value: &mut Value) {
+  trim_value_at(value, 0);
+}
+
+/// Trims `value`, nested `depth` objects or arrays deep.
+fn trim_value_at(value: &mut Value, depth: usize) {
+  let replacement = match *value {
+    Value::Object(_) if depth >= MAX_EXTRA_DEPTH => Value::String("[Object]".to_owned()),
+    Value::Array(_) if depth >= MAX_EXTRA_DEPTH => Value::String("[Array]".to_owned()),
+    Value::Object(ref mut map) => {
+      let extra_keys = map.keys().skip(MAX_EXTRA_ITEMS).cloned().collect::<Vec<String>>();
+      for key in extra_keys {
+        map.remove(&key);
+      }
+      for item in map.values_mut() {
+        trim_value_at(item, depth + 1);
+      }
+      return;
+    }
+    Value::Array(ref mut items) => {
+      items.truncate(MAX_EXTRA_ITEMS);
+      for item in items.iter_mut() {
+        trim_value_at(item, depth + 1);
+      }
+      return;
+    }
+    Value::String(ref string) if string.chars().count() > MAX_EXTRA_STRING_LENGTH => {
+      let mut trimmed = string.chars().take(MAX_EXTRA_STRING_LENGTH - 3).collect::<String>();
+      trimmed.push_str("...");
+      Value::String(trimmed)
+    }
+    _ => return,
+  };
+  *value = replacement;
+}
@@ -1,87 +1,439 @@
 //! Houses the implementation of anything for the "SingleWorker",
 //! Which is the single threaded worker for sentry.
+//!
+//! The worker supervises its closure: a panic while working on an item is caught, logged, and
+//! counted, and the item that caused it is dropped. The worker carries on with the next item straight
+//! away, so one bad item doesn't hold up the rest, fatal ones included, and a closure that panics on
+//! every item still only panics once per item.
+//!
+//! A worker can be given a capacity, the most items that may be waiting on it at once, so a Sentry
+//! that can't keep up doesn't grow the queue without bound.
+//!
+//! The thread is only spawned once there's something to work on. With an idle timeout it exits after
+//! going that long without work, and is spawned again by the next item, so a client that rarely
+//! captures anything doesn't keep a thread around for it. When the thread can't be spawned, such as in a
+//! sandbox that forbids it or at a thread limit, the item is refused rather than left waiting forever.
+//!
+//! A worker can be paused, leaving items in the queue until it's resumed, such as while the network is
+//! known to be down.
+//!
+//! Items sent with `work_ahead` skip the queue: they're worked on before anything sent with `work_with`,
+//! and taken even at capacity, so the event explaining an incident isn't stuck behind its backlog.
+//!
+//! Items wait behind a lock of their own, apart from the one the thread sleeps on, so sending rarely waits on
+//! the thread. With the `crossbeam` feature they wait in lock-free queues instead, so threads capturing
+//! during a burst don't wait on each other either.
 
-use ThreadState;
+use panic_message;
+use diagnostics;
+use error::CaptureError;
 use workers::WorkerClosure;
 
+#[cfg(feature = "crossbeam")]
+use crossbeam_queue::SegQueue;
+
+use std::cmp;
+use std::collections::VecDeque;
 use std::fmt::Debug;
-use std::sync::{Arc, Mutex};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::mpsc::{channel, Receiver, SendError, Sender};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::sync::atomic::{self, AtomicBool, AtomicUsize, Ordering};
 use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long the worker waits between attempts to spawn its thread, while its spawn timeout allows.
+const SPAWN_RETRY: Duration = Duration::from_millis(10);
+/// The name of the worker's thread.
+const THREAD_NAME: &'static str = "sentry-worker";
+
+/// Clears `alive` if the thread unwinds out of its loop, so the next item sent spawns a new one.
+struct AliveGuard(Arc<AtomicBool>);
+
+impl Drop for AliveGuard {
+  fn drop(&mut self) {
+    if thread::panicking() {
+      self.0.store(false, Ordering::SeqCst);
+    }
+  }
+}
+
+/// Where items wait for a worker's thread, urgent ones taken before the rest. Only the thread takes items,
+/// while any number of others send them.
+trait ItemQueue<T>: Send + Sync {
+  fn push(&self, item: T, urgent: bool);
+  fn pop(&self) -> Option<T>;
+}
+
+/// Items behind a lock of their own, held only to add or take one.
+struct LockedQueue<T> {
+  /// Sent with `work_ahead`, worked on before any of `normal`, and the normal items.
+  items: Mutex<(VecDeque<T>, VecDeque<T>)>,
+}
+
+impl<T: Send> ItemQueue<T> for LockedQueue<T> {
+  fn push(&self, item: T, urgent: bool) {
+    let mut items = match self.items.lock() {
+      Ok(guard) => guard,
+      Err(poisoned) => poisoned.into_inner(),
+    };
+    if urgent {
+      items.0.push_back(item);
+    } else {
+      items.1.push_back(item);
+    }
+  }
+
+  fn pop(&self) -> Option<T> {
+    let mut items = match self.items.lock() {
+      Ok(guard) => guard,
+      Err(poisoned) => poisoned.into_inner(),
+    };
+    items.0.pop_front().or_else(|| items.1.pop_front())
+  }
+}
+
+/// Items in lock-free queues, so threads capturing at once don't wait on each other to send.
+#[cfg(feature = "crossbeam")]
+struct LockFreeQueue<T> {
+  urgent: SegQueue<T>,
+  normal: SegQueue<T>,
+}
+
+#[cfg(feature = "crossbeam")]
+impl<T: Send> ItemQueue<T> for LockFreeQueue<T> {
+  fn push(&self, item: T, urgent: bool) {
+    if urgent {
+      self.urgent.push(item);
+    } else {
+      self.normal.push(item);
+    }
+  }
+
+  fn pop(&self) -> Option<T> {
+    self.urgent.pop().or_else(|_| self.normal.pop()).ok()
+  }
+}
+
+#[cfg(not(feature = "crossbeam"))]
+fn item_queue<T: 'static + Send>() -> Box<ItemQueue<T>> {
+  Box::new(LockedQueue {
+    items: Mutex::new((VecDeque::new(), VecDeque::new())),
+  })
+}
+
+#[cfg(feature = "crossbeam")]
+fn item_queue<T: 'static + Send>() -> Box<ItemQueue<T>> {
+  Box::new(LockFreeQueue {
+    urgent: SegQueue::new(),
+    normal: SegQueue::new(),
+  })
+}
+
+/// How a worker's thread runs, changed far less often than items are sent.
+struct Control {
+  /// How long the thread waits for an item before exiting, if it ever does.
+  idle_timeout: Option<Duration>,
+  /// How long spawning the thread is retried for before the item is refused.
+  spawn_timeout: Duration,
+}
+
+/// A worker's items, and the condition its thread waits on for them.
+///
+/// Sending an item doesn't take the control lock unless the thread is waiting or isn't running. The
+/// thread marks itself `sleeping` before looking for items one last time, and a sender looks at it after
+/// adding its item, so either the thread finds the item or the sender sees it has to wake it.
+struct SharedQueue<T> {
+  items: Box<ItemQueue<T>>,
+  control: Mutex<Control>,
+  ready: Condvar,
+  /// Set while the thread is waiting on `ready`, or about to.
+  sleeping: AtomicBool,
+  /// Set by `shutdown`, so no more items can be sent.
+  closed: AtomicBool,
+  /// Set by `pause`, so the thread leaves items where they are. Only changed with the control state locked.
+  paused: AtomicBool,
+}
+
+impl<T> SharedQueue<T> {
+  /// Locks the control state.
+  fn lock(&self) -> MutexGuard<Control> {
+    match self.control.lock() {
+      Ok(guard) => guard,
+      Err(poisoned) => poisoned.into_inner(),
+    }
+  }
+
+  /// Waits for the next item, urgent ones first, and for the worker not to be paused. Returns `None` once
+  /// the queue is closed and empty, or it's been empty for the idle timeout, clearing `alive` before the
+  /// control state is unlocked. A sender that finds the thread sleeping takes that lock too, so it always
+  /// sees an exiting thread isn't alive. A paused thread never exits idle, so it's there to resume.
+  fn next(&self, alive: &AtomicBool) -> Option<T> {
+    let idle_since = Instant::now();
+    loop {
+      if !self.paused.load(Ordering::SeqCst) {
+        if let Some(item) = self.items.pop() {
+          return Some(item);
+        }
+      }
+      let control = self.lock();
+      let paused = self.paused.load(Ordering::SeqCst);
+      self.sleeping.store(true, Ordering::SeqCst);
+      atomic::fence(Ordering::SeqCst);
+      if !paused {
+        if let Some(item) = self.items.pop() {
+          self.sleeping.store(false, Ordering::SeqCst);
+          return Some(item);
+        }
+      }
+      let idle_left = match control.idle_timeout {
+        Some(timeout) if !paused => Some(timeout.checked_sub(idle_since.elapsed())),
+        _ => None,
+      };
+      if self.closed.load(Ordering::SeqCst) || idle_left == Some(None) {
+        alive.store(false, Ordering::SeqCst);
+        self.sleeping.store(false, Ordering::SeqCst);
+        return None;
+      }
+      let _control = match idle_left {
+        Some(Some(left)) => match self.ready.wait_timeout(control, left) {
+          Ok((guard, _)) => guard,
+          Err(poisoned) => poisoned.into_inner().0,
+        },
+        _ => match self.ready.wait(control) {
+          Ok(guard) => guard,
+          Err(poisoned) => poisoned.into_inner(),
+        },
+      };
+      self.sleeping.store(false, Ordering::SeqCst);
+    }
+  }
+}
 
 /// A Single Worker thread that sends items to Sentry.
 pub struct SingleWorker<T: 'static + Send, P: Clone + Send> {
   parameters: P,
   f: Arc<Box<WorkerClosure<T, P, Output = ()>>>,
-  receiver: Arc<Mutex<Receiver<T>>>,
-  sender: Mutex<Sender<T>>,
+  queue: Arc<SharedQueue<T>>,
   alive: Arc<AtomicBool>,
+  restarts: Arc<AtomicUsize>,
+  /// How many items have been sent but not yet worked on.
+  pending: Arc<AtomicUsize>,
+  /// The most items that may be pending at once.
+  capacity: usize,
 }
 
 impl<T: 'static + Debug + Send, P: 'static + Clone + Send> SingleWorker<T, P> {
   /// Creates a new Worker Thread. This realaly should only be used internally, and you
   /// probably shouldn't just go around creating worker threads.
   pub fn new(parameters: P, f: Box<WorkerClosure<T, P, Output = ()>>) -> SingleWorker<T, P> {
-    let (sender, reciever) = channel::<T>();
+    SingleWorker::with_capacity(parameters, f, usize::MAX)
+  }
 
+  /// Creates a new Worker Thread that refuses items once `capacity` of them are waiting to be worked on.
+  /// The thread is spawned when the first item is sent.
+  pub fn with_capacity(
+    parameters: P,
+    f: Box<WorkerClosure<T, P, Output = ()>>,
+    capacity: usize,
+  ) -> SingleWorker<T, P> {
     let worker = SingleWorker {
       parameters: parameters,
       f: Arc::new(f),
-      receiver: Arc::new(Mutex::new(reciever)),
-      sender: Mutex::new(sender),
-      alive: Arc::new(AtomicBool::new(true)),
+      queue: Arc::new(SharedQueue {
+        items: item_queue(),
+        control: Mutex::new(Control {
+          idle_timeout: None,
+          spawn_timeout: Duration::from_millis(0),
+        }),
+        ready: Condvar::new(),
+        sleeping: AtomicBool::new(false),
+        closed: AtomicBool::new(false),
+        paused: AtomicBool::new(false),
+      }),
+      alive: Arc::new(AtomicBool::new(false)),
+      restarts: Arc::new(AtomicUsize::new(0)),
+      pending: Arc::new(AtomicUsize::new(0)),
+      capacity: capacity,
     };
-    SingleWorker::spawn_thread(&worker);
     worker
   }
 
-  /// Internal Method to handle some of the logic of reading from an a AtomicBoolean.
-  fn is_alive(&self) -> bool {
-    self.alive.clone().load(Ordering::Relaxed)
+  /// Whether the worker's thread is running. It isn't before the first item is sent, or once it's exited
+  /// after being idle or shut down.
+  pub fn is_alive(&self) -> bool {
+    self.alive.load(Ordering::SeqCst)
   }
 
-  /// Spawns the thread for when the worker isn't already working (alive).
-  fn spawn_thread(worker: &SingleWorker<T, P>) {
-    let mut alive = worker.alive.clone();
-    let f = worker.f.clone();
-    let receiver = worker.receiver.clone();
-    let parameters = worker.parameters.clone();
-    thread::spawn(move || {
-      let state = ThreadState { alive: &mut alive };
-      state.set_alive();
+  /// Whether the worker has been shut down, and no longer takes items.
+  pub fn is_closed(&self) -> bool {
+    self.queue.closed.load(Ordering::SeqCst)
+  }
 
-      let lock = match receiver.lock() {
-        Ok(guard) => guard,
-        Err(poisoned) => poisoned.into_inner(),
-      };
+  /// Stops the thread from taking items until `resume` is called, once it's done with any it's working on.
+  /// Items are still taken while paused, up to the worker's capacity, and wait for the worker to resume.
+  /// A worker that's been shut down can't be paused.
+  pub fn pause(&self) {
+    let _control = self.queue.lock();
+    if !self.queue.closed.load(Ordering::SeqCst) {
+      self.queue.paused.store(true, Ordering::SeqCst);
+    }
+  }
+
+  /// Lets the thread take items again after `pause`, starting with those sent while it was paused.
+  pub fn resume(&self) {
+    {
+      let _control = self.queue.lock();
+      self.queue.paused.store(false, Ordering::SeqCst);
+    }
+    self.queue.ready.notify_all();
+  }
+
+  /// Whether the worker has been paused, see `pause`.
+  pub fn is_paused(&self) -> bool {
+    self.queue.paused.load(Ordering::SeqCst)
+  }
+
+  /// Has the thread exit after going `timeout` without an item to work on, or never with `None`, the
+  /// default. The next item sent spawns it again.
+  pub fn set_idle_timeout(&self, timeout: Option<Duration>) {
+    self.queue.lock().idle_timeout = timeout;
+    // A waiting thread picks up the new timeout.
+    self.queue.ready.notify_all();
+  }
+
+  /// Keeps retrying to spawn the thread for up to `timeout` when the system refuses to start it, such as
+  /// at a thread limit, before the item that needed it is refused. Defaults to no retries, so the item is
+  /// refused at once.
+  pub fn set_spawn_timeout(&self, timeout: Duration) {
+    self.queue.lock().spawn_timeout = timeout;
+  }
+
+  /// How many times the worker has restarted after its closure panicked.
+  pub fn restarts(&self) -> usize {
+    self.restarts.load(Ordering::Relaxed)
+  }
 
-      loop {
-        match lock.recv() {
-          Ok(value) => f(&parameters, value),
-          Err(_) => {
-            thread::yield_now();
+  /// Spawns the thread for when the worker isn't already working (alive). Called with the control state
+  /// locked, so two senders can't both spawn one.
+  /// Spawning is retried until `spawn_timeout` has passed, then fails with `CaptureError::Spawn`.
+  fn spawn_thread(worker: &SingleWorker<T, P>, spawn_timeout: Duration) -> Result<(), CaptureError> {
+    worker.alive.store(true, Ordering::SeqCst);
+    let deadline = Instant::now() + spawn_timeout;
+    loop {
+      let alive = worker.alive.clone();
+      let f = worker.f.clone();
+      let queue = worker.queue.clone();
+      let parameters = worker.parameters.clone();
+      let restarts = worker.restarts.clone();
+      let pending = worker.pending.clone();
+      let spawned = thread::Builder::new().name(THREAD_NAME.to_owned()).spawn(move || {
+        let _internal = diagnostics::enter();
+        let _alive = AliveGuard(alive.clone());
+
+        // Once the queue is closed nothing more can arrive, so the loop ends after what's left.
+        while let Some(value) = queue.next(&alive) {
+          let result = panic::catch_unwind(AssertUnwindSafe(|| f(&parameters, value)));
+          pending.fetch_sub(1, Ordering::SeqCst);
+          if let Err(payload) = result {
+            let count = restarts.fetch_add(1, Ordering::Relaxed) + 1;
+            sdk_log!(
+              Info,
+              "Sentry worker panicked: {}, dropping the item and restarting ({} restarts).",
+              panic_message(&*payload),
+              count
+            );
           }
-        };
+        }
+      });
+
+      match spawned {
+        Ok(_) => return Ok(()),
+        Err(ref err) if Instant::now() < deadline => {
+          sdk_log!(Debug, "Sentry worker couldn't be spawned: {}, retrying.", err);
+          thread::sleep(cmp::min(SPAWN_RETRY, deadline - Instant::now()));
+        }
+        Err(err) => {
+          worker.alive.store(false, Ordering::SeqCst);
+          sdk_log!(Warn, "Sentry worker couldn't be spawned: {}, dropping the event.", err);
+          return Err(CaptureError::Spawn(err.to_string()));
+        }
       }
-    });
-    while !worker.is_alive() {
-      thread::yield_now();
     }
   }
 
-  /// Processes an Event that needs to go to Sentry.
-  pub fn work_with(&self, msg: T) -> Result<(), SendError<T>> {
-    let alive = self.is_alive();
-    if !alive {
-      SingleWorker::spawn_thread(self);
+  /// Processes an Event that needs to go to Sentry. Fails with `CaptureError::QueueFull` when the worker
+  /// is at capacity, `CaptureError::Closed` once it has been shut down, and `CaptureError::Spawn` when
+  /// its thread isn't running and can't be spawned.
+  pub fn work_with(&self, msg: T) -> Result<(), CaptureError> {
+    self.send(msg, false)
+  }
+
+  /// Processes an item before any sent with `work_with` that are still waiting, such as a fatal event
+  /// during a flood of warnings. It's taken even when the worker is at capacity. Fails with
+  /// `CaptureError::Closed` once the worker has been shut down, and `CaptureError::Spawn` when its thread
+  /// can't be spawned.
+  pub fn work_ahead(&self, msg: T) -> Result<(), CaptureError> {
+    self.send(msg, true)
+  }
+
+  /// Queues an item, at the back of the urgent or the normal items. The thread is spawned before the item
+  /// is added, so one that can't be is refused, and checked for again after, in case it exited meanwhile.
+  fn send(&self, msg: T, urgent: bool) -> Result<(), CaptureError> {
+    if self.queue.closed.load(Ordering::SeqCst) {
+      return Err(CaptureError::Closed);
+    }
+    if self.pending.fetch_add(1, Ordering::SeqCst) >= self.capacity && !urgent {
+      self.pending.fetch_sub(1, Ordering::SeqCst);
+      return Err(CaptureError::QueueFull);
     }
 
-    let lock = match self.sender.lock() {
-      Ok(guard) => guard,
-      Err(poisoned) => poisoned.into_inner(),
-    };
+    if !self.is_alive() {
+      let control = self.queue.lock();
+      if !self.is_alive() {
+        if let Err(err) = SingleWorker::spawn_thread(self, control.spawn_timeout) {
+          self.pending.fetch_sub(1, Ordering::SeqCst);
+          return Err(err);
+        }
+      }
+    }
+    self.queue.items.push(msg, urgent);
+
+    atomic::fence(Ordering::SeqCst);
+    if !self.is_alive() || self.queue.sleeping.load(Ordering::SeqCst) {
+      let control = self.queue.lock();
+      // The item is already queued, so if the thread can't be spawned now it waits for the next one.
+      if !self.is_alive() {
+        let _ = SingleWorker::spawn_thread(self, control.spawn_timeout);
+      }
+      self.queue.ready.notify_one();
+    }
+    Ok(())
+  }
 
-    lock.send(msg)
+  /// How many items have been sent but not yet worked on.
+  pub fn pending(&self) -> usize {
+    self.pending.load(Ordering::SeqCst)
+  }
+
+  /// Stops taking new items, and waits up to `timeout` for the worker to finish the ones already sent
+  /// and exit. Returns whether it did in time; if not, it keeps working through them in the background.
+  /// A paused worker is resumed, since nothing could resume it afterwards.
+  pub fn shutdown(&self, timeout: Duration) -> bool {
+    // Once the queue is closed the worker's loop ends, after whatever is still queued.
+    {
+      let _control = self.queue.lock();
+      self.queue.paused.store(false, Ordering::SeqCst);
+      self.queue.closed.store(true, Ordering::SeqCst);
+    }
+    self.queue.ready.notify_all();
+
+    let deadline = Instant::now() + timeout;
+    while self.is_alive() {
+      if Instant::now() >= deadline {
+        return false;
+      }
+      thread::sleep(Duration::from_millis(10));
+    }
+    true
   }
 }
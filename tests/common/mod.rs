@@ -0,0 +1,107 @@
+//! Helpers shared by the integration tests: clients sending to this machine, and a bare-bones stand-in
+//! for Sentry's store endpoint. Each test binary only uses some of them.
+#![allow(dead_code)]
+
+use sentry_rs::Sentry;
+use sentry_rs::models::{Scheme, SentryCredentials};
+use serde_json::{self, Value};
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::mpsc;
+use std::thread;
+
+/// A client sending to a server listening on `port` of this machine.
+pub fn local_sentry(port: u16) -> Sentry {
+  sentry_at(&format!("127.0.0.1:{}", port))
+}
+
+/// A client sending to `host`, which may include a port.
+pub fn sentry_at(host: &str) -> Sentry {
+  let credentials = SentryCredentials {
+    scheme: Scheme::Http,
+    key: "XXXX".to_owned(),
+    secret: Some("YYYY".to_owned()),
+    host: Some(host.to_owned()),
+    project_id: "1".to_owned(),
+  };
+  Sentry::new(
+    "Server Name".to_owned(),
+    "Release".to_owned(),
+    "Environment".to_owned(),
+    credentials,
+  )
+}
+
+/// A request read by `read_request`.
+pub struct ReceivedRequest {
+  /// The request line, such as `POST /api/1/store/ HTTP/1.1`.
+  pub line: String,
+  /// The headers, as sent.
+  pub headers: Vec<(String, String)>,
+  /// The body, `Content-Length` bytes long.
+  pub body: Vec<u8>,
+}
+
+impl ReceivedRequest {
+  /// The value of the header called `name`, ignoring case, if it was sent.
+  pub fn header(&self, name: &str) -> Option<&str> {
+    self
+      .headers
+      .iter()
+      .find(|&&(ref header, _)| header.eq_ignore_ascii_case(name))
+      .map(|&(_, ref value)| value.as_str())
+  }
+
+  /// The body parsed as JSON.
+  pub fn json(&self) -> Value {
+    serde_json::from_slice(&self.body).unwrap()
+  }
+}
+
+/// Reads the next request sent on a connection, or `None` once it's closed.
+pub fn read_request<R: BufRead>(reader: &mut R) -> Option<ReceivedRequest> {
+  let mut line = String::new();
+  if reader.read_line(&mut line).unwrap_or(0) == 0 {
+    return None;
+  }
+  let mut headers = Vec::new();
+  loop {
+    let mut header = String::new();
+    if reader.read_line(&mut header).unwrap_or(0) == 0 {
+      return None;
+    }
+    if header == "\r\n" {
+      break;
+    }
+    if let Some(colon) = header.find(':') {
+      headers.push((header[..colon].to_owned(), header[colon + 1..].trim().to_owned()));
+    }
+  }
+  let mut request = ReceivedRequest {
+    line: line.trim().to_owned(),
+    headers: headers,
+    body: Vec::new(),
+  };
+  let content_length = request.header("content-length").map_or(0, |length| length.parse().unwrap());
+  request.body = vec![0; content_length];
+  reader.read_exact(&mut request.body).ok()?;
+  Some(request)
+}
+
+/// Accepts events sent to `listener`, one per connection, passing each one on as JSON.
+pub fn serve_events(listener: TcpListener) -> mpsc::Receiver<Value> {
+  let (sender, receiver) = mpsc::channel();
+  thread::spawn(move || {
+    for stream in listener.incoming() {
+      let mut stream = stream.unwrap();
+      let request = match read_request(&mut BufReader::new(stream.try_clone().unwrap())) {
+        Some(request) => request,
+        None => continue,
+      };
+      let _ = sender.send(request.json());
+      let _ = write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+    }
+  });
+  receiver
+}
@@ -0,0 +1,69 @@
+#![cfg(feature = "mock_server")]
+
+extern crate sentry_rs;
+#[macro_use]
+extern crate serde_json;
+
+use sentry_rs::compat::{self as sentry, ClientOptions, Level};
+use sentry_rs::hub::Hub;
+use sentry_rs::mock_server::{MockServer, MOCK_KEY, MOCK_PROJECT_ID};
+
+use std::env;
+use std::thread;
+use std::time::Duration;
+
+// The client is bound to the process wide hub, so everything is checked in one test.
+#[test]
+pub fn init_binds_a_client_to_the_main_hub() {
+  env::remove_var(sentry::DSN_VAR);
+  let guard = sentry::init(ClientOptions::default());
+  assert!(!guard.is_enabled());
+  assert_eq!(sentry::capture_message("Dropped", Level::Info), "0".repeat(32));
+  drop(guard);
+
+  assert!(!sentry::init("not a dsn").is_enabled());
+
+  let server = MockServer::start().unwrap();
+  let dsn = format!("http://{}@{}/{}", MOCK_KEY, server.addr(), MOCK_PROJECT_ID);
+  let guard = sentry::init((
+    dsn.clone(),
+    ClientOptions {
+      release: Some("my-app@1.0.0".to_owned()),
+      environment: Some("staging".to_owned()),
+      ..Default::default()
+    },
+  ));
+  assert!(guard.is_enabled());
+
+  let event_id = sentry::capture_message("Started", Level::Warning);
+  let sent = server.wait_for_events(1, Duration::from_secs(5))[0].clone();
+  assert_eq!(sent["event_id"], json!(event_id));
+  assert_eq!(sent["message"], "Started");
+  assert_eq!(sent["level"], "warning");
+  assert_eq!(sent["release"], "my-app@1.0.0");
+  assert_eq!(sent["environment"], "staging");
+
+  // Threads started afterwards send through the main hub's client too.
+  let err = "ten".parse::<u32>().unwrap_err();
+  let event_id = thread::spawn(move || sentry::capture_error(&err)).join().unwrap();
+  let sent = server.wait_for_events(2, Duration::from_secs(5))[1].clone();
+  assert_eq!(sent["event_id"], json!(event_id));
+  assert_eq!(sent["level"], "error");
+  assert_eq!(sent["exception"]["values"][0]["type"], "ParseIntError");
+
+  drop(guard);
+  assert!(Hub::main().client().is_none());
+  assert!(Hub::current().client().is_none());
+
+  // An event left out by the sample rate isn't sent, so it has no id.
+  let guard = sentry::init((
+    dsn,
+    ClientOptions {
+      sample_rate: 0.0,
+      ..Default::default()
+    },
+  ));
+  assert!(guard.is_enabled());
+  assert_eq!(sentry::capture_message("Sampled out", Level::Error), "0".repeat(32));
+  assert_eq!(server.wait_for_events(3, Duration::from_millis(200)).len(), 2);
+}
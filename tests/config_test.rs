@@ -0,0 +1,117 @@
+extern crate sentry_rs;
+
+use sentry_rs::config::{ConfigError, SentryConfig};
+use sentry_rs::filter::{ErrorPattern, FingerprintStrategy, FrameLimit};
+use sentry_rs::models::{CredentialsParseError, EnvironmentError, Level};
+use sentry_rs::route::Route;
+
+#[test]
+pub fn config_is_read_from_json() {
+  let config = SentryConfig::from_json(
+    r#"{
+      "dsn": "https://key@sentry.example.com/1",
+      "server_name": "web-1",
+      "release": "1.2.3",
+      "dist": "arm64",
+      "additional_dsns": ["https://other@sentry.example.com/2"],
+      "sample_rate": 0.25,
+      "min_level": "warning",
+      "logger_levels": { "db::*": "error" },
+      "ignore_errors": ["Broken pipe"],
+      "ignore_error_patterns": ["^Connection reset"],
+      "tags": { "deny": ["auth_token"], "rename": { "usr": "user" } },
+      "trim_frames": true,
+      "frame_limit": { "top": 50, "bottom": 10 },
+      "max_breadcrumb_age_secs": 3600,
+      "fingerprint_strategy": "culprit_and_level",
+      "path_prefixes": { "/home/builder/": "", "/home/builder/vendor/": "vendor/" },
+      "panic_snapshot": { "args": true, "env_vars": ["RUST_LOG"] }
+    }"#,
+  ).unwrap();
+  assert_eq!(config.environment, "production");
+  assert_eq!(config.tags.deny, vec!["auth_token".to_owned()]);
+  assert_eq!(config.fingerprint_strategy, Some(FingerprintStrategy::CulpritAndLevel));
+  assert_eq!(config.frame_limit, Some(FrameLimit::new(50, 10)));
+  let snapshot = config.panic_snapshot.clone().unwrap();
+  assert!(snapshot.args && !snapshot.cwd);
+  assert!(snapshot.is_redacted("API_TOKEN"));
+
+  let options = config.options().unwrap();
+  assert_eq!(options.credentials.key, "key");
+  assert_eq!(options.release, "1.2.3");
+  assert_eq!(options.dist, Some("arm64".to_owned()));
+  assert_eq!(options.additional_credentials[0].key, "other");
+  assert_eq!(options.sample_rate, 0.25);
+  assert_eq!(
+    options.ignore_errors,
+    vec![
+      ErrorPattern::Exact("Broken pipe".to_owned()),
+      ErrorPattern::regex("^Connection reset").unwrap(),
+    ]
+  );
+
+  let sentry = config.build().unwrap();
+  assert_eq!(sentry.server_name, "web-1");
+  assert_eq!(sentry.options(), options);
+  assert_eq!(sentry.min_level(), Level::Warning);
+  assert_eq!(sentry.min_level_for("db::pool"), Level::Error);
+  let event = sentry.create_event("db", "error", "Query failed", None, None, None);
+  assert_eq!(event.dist, Some("arm64".to_owned()));
+}
+
+#[test]
+pub fn invalid_configs_are_refused() {
+  let parse = |json: &str| SentryConfig::from_json(json).and_then(|config| config.options());
+  match parse(r#"{"dsn": "https://key@sentry.example.com/1", "sample_rat": 1.0}"#) {
+    Err(ConfigError::Parse(_)) => {}
+    other => panic!("unknown field wasn't refused: {:?}", other),
+  }
+  assert_eq!(
+    parse(r#"{"additional_dsns": ["https://key@sentry.example.com/1"]}"#).unwrap_err(),
+    ConfigError::Credentials("dsn".to_owned(), CredentialsParseError::BadUrl)
+  );
+  assert_eq!(
+    parse(r#"{"dsn": "https://key@sentry.example.com/1", "environment": "None"}"#).unwrap_err(),
+    ConfigError::Environment(EnvironmentError::Reserved)
+  );
+  assert_eq!(
+    parse(r#"{"dsn": "https://key@sentry.example.com/1", "sample_rate": 1.5}"#).unwrap_err(),
+    ConfigError::SampleRate(1.5)
+  );
+
+  let config = SentryConfig::from_json(r#"{"dsn": "https://key@sentry.example.com/1", "min_level": "loud"}"#).unwrap();
+  assert_eq!(config.build().err(), Some(ConfigError::Level("loud".to_owned())));
+}
+
+#[test]
+pub fn reloading_a_config_keeps_the_routes() {
+  let config = SentryConfig::from_json(r#"{"dsn": "https://key@sentry.example.com/1"}"#).unwrap();
+  let sentry = config.build().unwrap();
+  let mut options = sentry.options();
+  let mut route = Route::new("https://ops@sentry.example.com/2".parse().unwrap());
+  route.levels.push(Level::Fatal);
+  options.routes = vec![route.clone()];
+  sentry.reconfigure(options);
+
+  let reloaded = SentryConfig::from_json(r#"{"dsn": "https://key@sentry.example.com/1", "release": "1.2.4"}"#).unwrap();
+  reloaded.apply(&sentry).unwrap();
+  assert_eq!(sentry.options().release, "1.2.4");
+  assert_eq!(sentry.options().routes, vec![route]);
+}
+
+#[cfg(feature = "toml")]
+#[test]
+pub fn config_is_read_from_toml() {
+  let config = SentryConfig::from_toml(
+    r#"
+      dsn = "https://key@sentry.example.com/1"
+      environment = "staging"
+
+      [tags]
+      allow = ["user"]
+    "#,
+  ).unwrap();
+  assert_eq!(config.environment, "staging");
+  assert_eq!(config.tags.allow, vec!["user".to_owned()]);
+  assert_eq!(config.options().unwrap().credentials.key, "key");
+}
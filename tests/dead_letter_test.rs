@@ -0,0 +1,80 @@
+extern crate sentry_rs;
+
+use sentry_rs::dead_letter::DeadLetterFile;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+pub fn scratch_path(name: &str) -> PathBuf {
+  let path = env::temp_dir().join(format!("sentry-rs-{}-{}.ndjson", name, std::process::id()));
+  let _ = fs::remove_file(&path);
+  path
+}
+
+#[test]
+pub fn dead_letters_are_taken_in_order() {
+  let dead_letters = DeadLetterFile::new(scratch_path("order"), 1024);
+  dead_letters.append("{\"a\":1}").unwrap();
+  dead_letters.append("{\"b\":2}").unwrap();
+
+  assert_eq!(
+    dead_letters.take().unwrap(),
+    vec!["{\"a\":1}".to_owned(), "{\"b\":2}".to_owned()]
+  );
+  assert!(dead_letters.take().unwrap().is_empty());
+}
+
+#[test]
+pub fn dead_letters_rotate_past_max_bytes() {
+  // Each record is 43 bytes, so two fit.
+  let dead_letters = DeadLetterFile::new(scratch_path("rotate"), 100);
+  let _ = fs::remove_file(dead_letters.rotated_path());
+  dead_letters.append("{\"a\":1}").unwrap();
+  dead_letters.append("{\"b\":2}").unwrap();
+  dead_letters.append("{\"c\":3}").unwrap();
+
+  let rotated = DeadLetterFile::new(dead_letters.rotated_path(), 100);
+  assert_eq!(rotated.read().unwrap(), vec!["{\"a\":1}".to_owned(), "{\"b\":2}".to_owned()]);
+  assert_eq!(fs::metadata(&dead_letters.path).unwrap().len(), 43);
+  assert_eq!(dead_letters.take().unwrap().len(), 3);
+}
+
+#[test]
+pub fn dead_letters_are_stored_as_versioned_records() {
+  let dead_letters = DeadLetterFile::new(scratch_path("records"), 1024);
+  dead_letters.append("{\"message\":\"two\nlines\"}").unwrap();
+
+  assert_eq!(
+    fs::read_to_string(&dead_letters.path).unwrap(),
+    "sentry-rs-dead-letter 1 23 e9d89862\n{\"message\":\"two\nlines\"}\n"
+  );
+  assert_eq!(dead_letters.read().unwrap(), vec!["{\"message\":\"two\nlines\"}".to_owned()]);
+  assert_eq!(dead_letters.take().unwrap().len(), 1);
+}
+
+#[test]
+pub fn dead_letters_keep_records_they_cannot_read() {
+  let dead_letters = DeadLetterFile::new(scratch_path("versions"), 1024);
+  let newer = "sentry-rs-dead-letter 9 10 zstd\n{\"from\":9}\n";
+  fs::write(
+    &dead_letters.path,
+    format!(
+      "{{\"legacy\":0}}\n{}sentry-rs-dead-letter 1 7 00000000\n{{\"a\":1}}\nsentry-rs-dead-letter 1 40 0",
+      newer
+    ),
+  ).unwrap();
+  dead_letters.append("{\"b\":2}").unwrap();
+
+  // Lines from before records were versioned are read as they are. The record with a bad checksum, and the
+  // one cut short, are skipped, without losing the record appended after it.
+  assert_eq!(
+    dead_letters.take().unwrap(),
+    vec!["{\"legacy\":0}".to_owned(), "{\"b\":2}".to_owned()]
+  );
+  // The record from a newer version is left for that version to read.
+  assert_eq!(fs::read_to_string(&dead_letters.path).unwrap(), newer);
+  assert!(dead_letters.take().unwrap().is_empty());
+  assert_eq!(fs::read_to_string(&dead_letters.path).unwrap(), newer);
+  let _ = fs::remove_file(&dead_letters.path);
+}
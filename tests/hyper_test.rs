@@ -0,0 +1,155 @@
+extern crate futures;
+extern crate hyper;
+extern crate sentry_rs;
+#[macro_use]
+extern crate serde_json;
+
+mod common;
+
+use common::{local_sentry, serve_events};
+use futures::Future;
+use futures::future::{self, FutureResult};
+use hyper::{Error as HyperError, Method, Request as ClientRequest, StatusCode};
+use hyper::server::{Request, Response, Service};
+use sentry_rs::Sentry;
+use sentry_rs::integrations::hyper::{SentryDispatcher, SentryService};
+use sentry_rs::models::Breadcrumb;
+use sentry_rs::request::{DispatchErrorKind, DispatchRequest, HttpDispatchError, HttpResponse};
+
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+
+/// How `Answering` answers each request.
+#[derive(Clone, Copy)]
+enum Answer {
+  Hello,
+  Error,
+  PanicInCall,
+  PanicInPoll,
+}
+
+/// A service answering every request the same way.
+struct Answering(Answer);
+
+impl Service for Answering {
+  type Request = Request;
+  type Response = Response;
+  type Error = HyperError;
+  type Future = Box<Future<Item = Response, Error = HyperError>>;
+
+  fn call(&self, _: Request) -> Self::Future {
+    match self.0 {
+      Answer::Hello => Box::new(future::ok(Response::new().with_body("Hello!"))),
+      Answer::Error => Box::new(future::err(HyperError::Timeout)),
+      Answer::PanicInCall => panic!("The service fell over"),
+      Answer::PanicInPoll => Box::new(future::lazy(|| -> Result<Response, HyperError> {
+        panic!("The response fell over")
+      })),
+    }
+  }
+}
+
+/// A dispatcher failing every request it's given.
+struct Refusing;
+
+impl DispatchRequest for Refusing {
+  type Future = FutureResult<HttpResponse, HttpDispatchError>;
+
+  fn dispatch(&self, _: ClientRequest, _: Option<Duration>) -> Self::Future {
+    future::err(HttpDispatchError::new(DispatchErrorKind::Connection, "Connection refused"))
+  }
+}
+
+/// A client sending to a local stand-in for Sentry, and the events it receives.
+fn local() -> (Arc<Sentry>, Receiver<serde_json::Value>) {
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let sentry = Arc::new(local_sentry(listener.local_addr().unwrap().port()));
+  (sentry, serve_events(listener))
+}
+
+/// A `GET` of `path` on `example.com`, sending a secret.
+fn request(path: &str) -> Request {
+  let mut request = Request::new(Method::Get, path.parse().unwrap());
+  request.headers_mut().set_raw("Host", "example.com");
+  request.headers_mut().set_raw("Authorization", "Bearer secret");
+  request
+}
+
+/// The breadcrumbs an event created now would carry.
+fn breadcrumbs(sentry: &Sentry) -> Vec<Breadcrumb> {
+  sentry.create_event("test", "info", "Breadcrumbs", None, None, None).breadcrumbs
+}
+
+#[test]
+pub fn handled_requests_are_recorded_as_breadcrumbs() {
+  let (sentry, _) = local();
+  let service = SentryService::new(sentry.clone(), Answering(Answer::Hello));
+  let response = service.call(request("/users")).wait().unwrap();
+  assert_eq!(response.status(), StatusCode::Ok);
+
+  let breadcrumbs = breadcrumbs(&sentry);
+  let breadcrumb = breadcrumbs.last().unwrap();
+  assert_eq!(breadcrumb.category, Some("http.server".to_owned()));
+  assert_eq!(breadcrumb.level, "info");
+  assert_eq!(breadcrumb.data["method"], json!("GET"));
+  assert_eq!(breadcrumb.data["url"], json!("http://example.com/users"));
+  assert_eq!(breadcrumb.data["status_code"], json!(200));
+}
+
+#[test]
+pub fn errors_are_reported_with_the_redacted_request() {
+  let (sentry, served) = local();
+  let service = SentryService::new(sentry.clone(), Answering(Answer::Error));
+  assert!(service.call(request("/users?token=abc&page=2")).wait().is_err());
+
+  let event = served.recv_timeout(Duration::from_secs(5)).unwrap();
+  assert_eq!(event["logger"], json!("hyper"));
+  assert_eq!(event["level"], json!("error"));
+  assert_eq!(event["transaction"], json!("/users"));
+  assert_eq!(event["culprit"], json!("GET http://example.com/users"));
+  assert_eq!(event["request"]["url"], json!("http://example.com/users"));
+  assert_eq!(event["request"]["query_string"], json!("token=[redacted]&page=2"));
+  assert_eq!(event["request"]["headers"]["Authorization"], json!("[redacted]"));
+  assert_eq!(event["request"]["headers"]["Host"], json!("example.com"));
+  assert!(breadcrumbs(&sentry).last().unwrap().data.get("status_code").is_none());
+}
+
+#[test]
+pub fn panics_are_reported_once_and_answered_with_a_500() {
+  let (sentry, served) = local();
+  for &(answer, message) in [
+    (Answer::PanicInCall, "The service fell over"),
+    (Answer::PanicInPoll, "The response fell over"),
+  ].iter()
+  {
+    let service = SentryService::new(sentry.clone(), Answering(answer));
+    let response = service.call(request("/users")).wait().unwrap();
+    assert_eq!(response.status(), StatusCode::InternalServerError);
+
+    let event = served.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(event["message"], json!(message));
+    assert_eq!(event["level"], json!("fatal"));
+    assert_eq!(event["logger"], json!("hyper"));
+    assert_eq!(event["request"]["method"], json!("GET"));
+    assert_eq!(event["exception"]["values"][0]["mechanism"]["handled"], json!(false));
+  }
+  assert!(served.recv_timeout(Duration::from_millis(200)).is_err());
+}
+
+#[test]
+pub fn failed_outgoing_requests_are_recorded_as_warnings() {
+  let (sentry, _) = local();
+  let client = SentryDispatcher::new(sentry.clone(), Refusing);
+  let request = ClientRequest::new(Method::Post, "http://example.com/upload".parse().unwrap());
+  assert!(client.dispatch(request, None).wait().is_err());
+
+  let breadcrumbs = breadcrumbs(&sentry);
+  let breadcrumb = breadcrumbs.last().unwrap();
+  assert_eq!(breadcrumb.category, Some("http".to_owned()));
+  assert_eq!(breadcrumb.level, "warning");
+  assert_eq!(breadcrumb.data["method"], json!("POST"));
+  assert_eq!(breadcrumb.data["url"], json!("http://example.com/upload"));
+  assert_eq!(breadcrumb.data["reason"], json!("Connection refused"));
+}
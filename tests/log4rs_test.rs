@@ -0,0 +1,80 @@
+#![cfg(feature = "log4rs")]
+
+extern crate log;
+extern crate log4rs;
+extern crate sentry_rs;
+#[macro_use]
+extern crate serde_json;
+
+mod common;
+
+use common::{local_sentry, serve_events};
+use log::{Level, LevelFilter, Record};
+use log4rs::append::Append;
+use log4rs::config::{Deserialize, Deserializers};
+use sentry_rs::integrations::log4rs::{SentryAppender, SentryAppenderConfig, SentryAppenderDeserializer};
+
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Appends a record from the `checkout` target at `level`.
+fn append(appender: &Append, level: Level, message: &str) {
+  appender
+    .append(&Record::builder().level(level).target("checkout").args(format_args!("{}", message)).build())
+    .unwrap();
+}
+
+/// The config of an appender sending to `dsn`, with everything else left to its defaults.
+fn config(dsn: &str) -> SentryAppenderConfig {
+  SentryAppenderConfig {
+    dsn: dsn.to_owned(),
+    threshold: None,
+    logger: None,
+    server_name: None,
+    release: None,
+    environment: None,
+  }
+}
+
+#[test]
+pub fn records_past_the_threshold_are_dropped() {
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let sentry = Arc::new(local_sentry(listener.local_addr().unwrap().port()));
+  let served = serve_events(listener);
+  let appender = SentryAppender::new(sentry, LevelFilter::Warn, Some("my_app".to_owned()));
+
+  append(&appender, Level::Info, "Cart loaded");
+  append(&appender, Level::Warn, "Payment slow");
+
+  let event = served.recv_timeout(Duration::from_secs(5)).unwrap();
+  assert_eq!(event["message"], json!("Payment slow"));
+  assert_eq!(event["level"], json!("warning"));
+  assert_eq!(event["logger"], json!("my_app"));
+  assert!(served.recv_timeout(Duration::from_millis(200)).is_err());
+}
+
+#[test]
+pub fn configured_appenders_default_to_errors_from_the_record_target() {
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let dsn = format!("http://XXXX:YYYY@{}/1", listener.local_addr().unwrap());
+  let served = serve_events(listener);
+  let appender = SentryAppenderDeserializer.deserialize(config(&dsn), &Deserializers::default()).unwrap();
+
+  append(&*appender, Level::Warn, "Payment slow");
+  append(&*appender, Level::Error, "Charge failed");
+
+  let event = served.recv_timeout(Duration::from_secs(5)).unwrap();
+  assert_eq!(event["message"], json!("Charge failed"));
+  assert_eq!(event["level"], json!("error"));
+  assert_eq!(event["logger"], json!("checkout"));
+  assert!(served.recv_timeout(Duration::from_millis(200)).is_err());
+}
+
+#[test]
+pub fn configs_with_a_bad_dsn_are_refused() {
+  let err = SentryAppenderDeserializer
+    .deserialize(config("not a dsn"), &Deserializers::default())
+    .unwrap_err();
+  assert!(err.to_string().starts_with("Invalid Sentry DSN"), "{}", err);
+}
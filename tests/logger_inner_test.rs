@@ -0,0 +1,78 @@
+//! `init_with_inner` installs the global logger, which can only happen once a process, so it gets a test
+//! binary of its own.
+
+#[macro_use]
+extern crate log;
+extern crate sentry_rs;
+#[macro_use]
+extern crate serde_json;
+
+mod common;
+
+use common::{local_sentry, serve_events};
+use log::{LevelFilter, Log, Metadata, Record};
+use sentry_rs::diagnostics;
+use sentry_rs::logger::SentryLogger;
+
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A logger keeping the target and message of every record it's given.
+struct Recording(Arc<Mutex<Vec<(String, String)>>>);
+
+impl Log for Recording {
+  fn enabled(&self, _: &Metadata) -> bool {
+    true
+  }
+
+  fn log(&self, record: &Record) {
+    self
+      .0
+      .lock()
+      .unwrap()
+      .push((record.target().to_owned(), format!("{}", record.args())));
+  }
+
+  fn flush(&self) {}
+}
+
+#[test]
+pub fn records_are_forwarded_to_the_inner_logger_without_recursing_into_sentry() {
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let sentry = Arc::new(local_sentry(listener.local_addr().unwrap().port()));
+  let served = serve_events(listener);
+  let records = Arc::new(Mutex::new(Vec::new()));
+  SentryLogger::new(sentry, LevelFilter::Error)
+    .init_with_inner(Recording(records.clone()), LevelFilter::Info)
+    .unwrap();
+
+  info!(target: "my_app", "Cart loaded");
+  error!(target: "my_app", "Charge failed");
+  {
+    let _internal = diagnostics::enter();
+    error!(target: "my_app", "Logged while sending");
+  }
+
+  let event = served.recv_timeout(Duration::from_secs(5)).unwrap();
+  assert_eq!(event["message"], json!("Charge failed"));
+  assert_eq!(event["logger"], json!("my_app"));
+  // Sending the event logs from the SDK, which must not become yet another event.
+  assert!(served.recv_timeout(Duration::from_millis(500)).is_err());
+
+  let records = records.lock().unwrap();
+  for message in ["Cart loaded", "Charge failed", "Logged while sending"].iter() {
+    assert!(
+      records.iter().any(|&(ref target, ref m)| target == "my_app" && m == message),
+      "{:?}",
+      *records
+    );
+  }
+  assert!(
+    records
+      .iter()
+      .any(|&(ref target, ref m)| target.starts_with("sentry_rs") && m == "Post has been called for Sentry!"),
+    "{:?}",
+    *records
+  );
+}
@@ -0,0 +1,141 @@
+#![cfg(feature = "mock_server")]
+
+extern crate hyper;
+extern crate sentry_rs;
+#[macro_use]
+extern crate serde_json;
+
+use hyper::StatusCode;
+use sentry_rs::Sentry;
+use sentry_rs::dead_letter::DeadLetterFile;
+use sentry_rs::error::{CaptureError, SendError};
+use sentry_rs::mock_server::{MockResponse, MockServer, MOCK_KEY};
+
+use std::env;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+/// A client sending to `server`.
+pub fn sentry_for(server: &MockServer) -> Sentry {
+  Sentry::new(
+    "Server Name".to_owned(),
+    "Release".to_owned(),
+    "Environment".to_owned(),
+    server.credentials(),
+  )
+}
+
+#[test]
+pub fn stored_events_are_recorded() {
+  let server = MockServer::start().unwrap();
+  let sentry = sentry_for(&server);
+  let event = sentry.create_event("test", "error", "Recorded", None, None, None);
+  let event_id = event.event_id.clone();
+  assert!(sentry.capture_event_sync(event, Duration::from_secs(5)).is_ok());
+
+  let events = server.wait_for_events(1, Duration::from_secs(5));
+  assert_eq!(events.len(), 1);
+  assert_eq!(events[0]["event_id"], json!(event_id));
+  assert_eq!(events[0]["message"], "Recorded");
+  let requests = server.requests();
+  assert_eq!(requests[0].method, "POST");
+  assert_eq!(requests[0].path, "/api/1/store/");
+  assert!(requests[0].header("x-sentry-auth").unwrap().contains(&format!("sentry_key={}", MOCK_KEY)));
+
+  server.reset();
+  assert!(server.requests().is_empty());
+}
+
+#[test]
+pub fn queued_responses_answer_requests_in_order() {
+  let server = MockServer::start().unwrap();
+  let sentry = sentry_for(&server);
+  server.respond_with(MockResponse::rate_limited(Duration::from_secs(60)));
+  server.respond_with(MockResponse::server_error());
+  server.respond_with(MockResponse::rejected("Discarded invalid value"));
+
+  let mut statuses = Vec::new();
+  for _ in 0..4 {
+    let event = sentry.create_event("test", "error", "Answered", None, None, None);
+    match sentry.capture_event_sync(event, Duration::from_secs(5)) {
+      Ok(_) => statuses.push((StatusCode::Ok, None)),
+      Err(CaptureError::Send(SendError::Status(rejection))) => statuses.push((rejection.status, rejection.reason)),
+      Err(err) => panic!("expected a response, got {:?}", err),
+    }
+  }
+  assert_eq!(
+    statuses,
+    vec![
+      (StatusCode::TooManyRequests, None),
+      (StatusCode::InternalServerError, None),
+      (StatusCode::BadRequest, Some("Discarded invalid value".to_owned())),
+      (StatusCode::Ok, None),
+    ]
+  );
+  assert_eq!(server.requests()[0].path, "/api/1/store/");
+  assert_eq!(server.events().len(), 4);
+}
+
+#[test]
+pub fn only_retryable_failures_are_kept_as_dead_letters() {
+  let server = MockServer::start().unwrap();
+  let sentry = sentry_for(&server);
+  let path = env::temp_dir().join(format!("sentry-rs-mock-server-{}.ndjson", std::process::id()));
+  let _ = fs::remove_file(&path);
+  let dead_letters = DeadLetterFile::new(path.clone(), 1024 * 1024);
+  sentry.set_dead_letter_file(dead_letters.clone());
+  server.respond_with(MockResponse::rejected("Discarded invalid value"));
+  server.respond_with(MockResponse::new(StatusCode::PayloadTooLarge));
+  server.respond_with(MockResponse::rate_limited(Duration::from_secs(0)));
+  server.respond_with(MockResponse::server_error());
+
+  let mut retryable = Vec::new();
+  for message in &["Invalid", "Too large", "Rate limited", "Failed"] {
+    let event = sentry.create_event("test", "error", message, None, None, None);
+    match sentry.capture_event_sync(event, Duration::from_secs(5)) {
+      Err(CaptureError::Send(err)) => retryable.push(err.is_retryable()),
+      other => panic!("expected a send error, got {:?}", other),
+    }
+  }
+  assert_eq!(retryable, vec![false, false, true, true]);
+  let kept = dead_letters
+    .read()
+    .unwrap()
+    .iter()
+    .map(|body| serde_json::from_str::<serde_json::Value>(body).unwrap()["message"].clone())
+    .collect::<Vec<_>>();
+  assert_eq!(kept, vec![json!("Rate limited"), json!("Failed")]);
+
+  server.respond_with(MockResponse::rejected("Discarded invalid value"));
+  assert_eq!(sentry.resubmit_dead_letters().unwrap(), 1);
+  assert!(dead_letters.read().unwrap().is_empty());
+  let _ = fs::remove_file(&path);
+}
+
+#[test]
+pub fn envelope_events_are_recorded() {
+  let server = MockServer::start().unwrap();
+  let event = r#"{"event_id":"fc6d8c0c43fc4630ad850ee518f1b9d0","message":"Enveloped"}"#;
+  let body = format!(
+    "{{\"event_id\":\"fc6d8c0c43fc4630ad850ee518f1b9d0\"}}\n{{\"type\":\"attachment\",\"length\":5}}\nhello\n\
+     {{\"type\":\"event\",\"length\":{}}}\n{}\n{{\"type\":\"session\"}}\n{{\"status\":\"ok\"}}\n",
+    event.len(),
+    event
+  );
+  let mut stream = TcpStream::connect(server.addr()).unwrap();
+  write!(
+    stream,
+    "POST /api/1/envelope/ HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+    server.addr(),
+    body.len(),
+    body
+  ).unwrap();
+  let mut response = String::new();
+  stream.read_to_string(&mut response).unwrap();
+  assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+
+  let events = server.wait_for_events(1, Duration::from_secs(5));
+  assert_eq!(events, vec![json!({"event_id": "fc6d8c0c43fc4630ad850ee518f1b9d0", "message": "Enveloped"})]);
+}
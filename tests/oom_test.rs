@@ -0,0 +1,50 @@
+// With the `nightly` feature only failures that abort are reported, which a test can't survive.
+#![cfg(not(feature = "nightly"))]
+
+extern crate sentry_rs;
+extern crate serde_json;
+
+mod common;
+
+use common::{local_sentry, serve_events};
+use sentry_rs::integrations::oom::{self, OomReporting};
+use sentry_rs::models::Breadcrumb;
+
+use std::alloc::{self, Layout, System};
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[global_allocator]
+static ALLOCATOR: OomReporting<System> = OomReporting::new(System);
+
+// The allocator is shared by the whole process, so it's tested in its own binary.
+#[test]
+pub fn failed_allocations_are_reported_each_time() {
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let sentry = Arc::new(local_sentry(listener.local_addr().unwrap().port()));
+  sentry.add_breadcrumb(Breadcrumb::new(Some("test"), "info", Some("before registering")));
+  let served = serve_events(listener);
+  oom::register_oom_handler(sentry, Duration::from_secs(5));
+
+  // Far more than any machine has, so the allocation fails without taking the test down with it.
+  let layout = Layout::from_size_align(isize::MAX as usize / 2, 1).unwrap();
+  assert!(unsafe { alloc::alloc(layout) }.is_null());
+  let sent = served.recv_timeout(Duration::from_secs(5)).unwrap();
+  assert_eq!(sent["message"], "Out of memory");
+  assert_eq!(sent["level"], "fatal");
+  assert_eq!(sent["logger"], "oom");
+  assert_eq!(sent["extra"]["allocation_size"], layout.size());
+  assert!(sent["breadcrumbs"].is_null());
+
+  // A failure the program handles itself can't be told apart, so a later one is reported as well.
+  let smaller = Layout::from_size_align(isize::MAX as usize / 4, 1).unwrap();
+  assert!(unsafe { alloc::alloc(smaller) }.is_null());
+  let again = served.recv_timeout(Duration::from_secs(5)).unwrap();
+  assert_eq!(again["extra"]["allocation_size"], smaller.size());
+  assert!(again["event_id"] != sent["event_id"]);
+  oom::unregister_oom_handler();
+
+  assert!(unsafe { alloc::alloc(layout) }.is_null());
+  assert!(served.recv_timeout(Duration::from_millis(200)).is_err());
+}
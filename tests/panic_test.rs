@@ -0,0 +1,95 @@
+extern crate sentry_rs;
+#[macro_use]
+extern crate serde_json;
+
+mod common;
+
+use common::{local_sentry, serve_events};
+use sentry_rs::PanicDelivery;
+use sentry_rs::models::Event;
+use sentry_rs::snapshot::ProcessSnapshot;
+
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// The panic hook is shared by the whole process, so it's tested in its own binary, with one test so
+// handlers registered by different tests don't replace each other.
+#[test]
+pub fn panic_handler_waits_for_its_own_event() {
+  // Never accepted, so the event sits waiting for a response until the request times out.
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let sentry = local_sentry(listener.local_addr().unwrap().port());
+  sentry.set_panic_wait(Duration::from_millis(200));
+
+  let called = Arc::new(AtomicBool::new(false));
+  let seen = called.clone();
+  sentry.register_panic_handler_with_delivery(
+    PanicDelivery::Queue,
+    Some(move |_: &std::panic::PanicInfo| seen.store(true, Ordering::SeqCst)),
+  );
+
+  let start = Instant::now();
+  assert!(thread::spawn(|| panic!("Nobody is listening")).join().is_err());
+  assert!(start.elapsed() < Duration::from_secs(3));
+  assert!(called.load(Ordering::SeqCst));
+  sentry.unregister_panic_handler();
+
+  // Each panic waits on its own event, so concurrent panics can't take each other's notifications.
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let sentry = local_sentry(listener.local_addr().unwrap().port());
+  let served = serve_events(listener);
+  sentry.register_panic_handler_with_delivery(PanicDelivery::Queue, None::<fn(&std::panic::PanicInfo)>);
+
+  let start = Instant::now();
+  let panics = (0..4)
+    .map(|_| thread::spawn(|| panic!("Everyone fell over at once")))
+    .collect::<Vec<_>>();
+  for panicked in panics {
+    assert!(panicked.join().is_err());
+  }
+  assert!(start.elapsed() < Duration::from_secs(4));
+  for _ in 0..4 {
+    assert!(served.recv_timeout(Duration::from_secs(1)).is_ok());
+  }
+
+  // The event can be changed, or dropped, before it's queued.
+  sentry.set_before_panic_capture(|event: &mut Event| {
+    event.tags.insert("crashed".to_owned(), "true".to_owned());
+    !event.message.starts_with("Shutting down")
+  });
+  assert!(thread::spawn(|| panic!("Shutting down")).join().is_err());
+  assert!(thread::spawn(|| panic!("Out of disk")).join().is_err());
+  let sent = served.recv_timeout(Duration::from_secs(1)).unwrap();
+  assert_eq!(sent["message"], "Out of disk");
+  assert_eq!(sent["tags"]["crashed"], "true");
+  assert_eq!(sent["exception"]["values"][0]["value"], "Out of disk");
+  assert_eq!(sent["exception"]["values"][0]["mechanism"], json!({"type": "panic", "handled": false}));
+  assert!(served.recv_timeout(Duration::from_millis(200)).is_err());
+  sentry.clear_before_panic_capture();
+
+  // How the process was run can be sent along, with secrets redacted.
+  std::env::set_var("SNAPSHOT_TEST_REGION", "eu-west-1");
+  std::env::set_var("SNAPSHOT_TEST_TOKEN", "hunter2");
+  let mut snapshot = ProcessSnapshot::new();
+  snapshot.env_vars = vec![
+    "SNAPSHOT_TEST_REGION".to_owned(),
+    "SNAPSHOT_TEST_TOKEN".to_owned(),
+    "SNAPSHOT_TEST_UNSET".to_owned(),
+  ];
+  snapshot.cwd = true;
+  snapshot.args = true;
+  sentry.set_panic_snapshot(Some(snapshot));
+  assert!(thread::spawn(|| panic!("Bad arguments")).join().is_err());
+  let sent = served.recv_timeout(Duration::from_secs(1)).unwrap();
+  assert_eq!(
+    sent["extra"]["env"],
+    json!({"SNAPSHOT_TEST_REGION": "eu-west-1", "SNAPSHOT_TEST_TOKEN": "[redacted]"})
+  );
+  assert_eq!(sent["extra"]["cwd"], std::env::current_dir().unwrap().to_str().unwrap());
+  let args = std::env::args().collect::<Vec<_>>();
+  assert_eq!(sent["extra"]["args"][0], args[0].as_str());
+  sentry.unregister_panic_handler();
+}
@@ -0,0 +1,47 @@
+#![cfg(unix)]
+
+extern crate sentry_rs;
+#[macro_use]
+extern crate serde_json;
+
+mod common;
+
+use common::{local_sentry, serve_events};
+use sentry_rs::integrations::process;
+
+use std::net::TcpListener;
+use std::process::Command;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[test]
+pub fn failed_children_are_reported() {
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let port = listener.local_addr().unwrap().port();
+  let served = serve_events(listener);
+  let sentry = Arc::new(local_sentry(port));
+
+  let mut child = process::spawn(sentry.clone(), Command::new("sh").arg("-c").arg("exit 0")).unwrap();
+  assert!(child.wait().unwrap().success());
+  assert!(served.recv_timeout(Duration::from_millis(200)).is_err());
+
+  let script = "for i in $(seq 1 60); do echo line $i >&2; done; exit 3";
+  let mut command = Command::new("sh");
+  command.args(&["-c", script, "backup", "--api-token", "hunter2"]);
+  let mut child = process::spawn(sentry.clone(), &mut command).unwrap();
+  assert_eq!(child.wait().unwrap().code(), Some(3));
+  let sent = served.recv_timeout(Duration::from_secs(5)).unwrap();
+  assert_eq!(sent["message"], "Child process sh exited with code 3");
+  assert_eq!(sent["tags"]["exit_code"], "3");
+  assert_eq!(sent["extra"]["command"], json!(["sh", "-c", script, "backup", "--api-token", "[redacted]"]));
+  let stderr = sent["extra"]["stderr"].as_str().unwrap().lines().collect::<Vec<_>>();
+  assert_eq!(stderr.len(), process::STDERR_TAIL_LINES);
+  assert_eq!(stderr[0], "line 11");
+  assert_eq!(stderr[49], "line 60");
+
+  let mut child = process::spawn(sentry.clone(), Command::new("sh").args(&["-c", "kill -9 $$"])).unwrap();
+  assert!(!child.wait().unwrap().success());
+  let sent = served.recv_timeout(Duration::from_secs(5)).unwrap();
+  assert_eq!(sent["message"], "Child process sh was killed by SIGKILL (9)");
+  assert_eq!(sent["tags"]["signal"], "SIGKILL");
+}
@@ -0,0 +1,57 @@
+#![cfg(windows)]
+
+extern crate sentry_rs;
+#[macro_use]
+extern crate serde_json;
+
+mod common;
+
+use common::{local_sentry, serve_events};
+use sentry_rs::integrations::seh;
+
+use std::env;
+use std::net::TcpListener;
+use std::process::Command;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Set for the copy of this test binary that crashes, to the port to report the crash to.
+const CRASH_PORT: &'static str = "SENTRY_RS_TEST_CRASH_PORT";
+
+// Crashing takes the process down, so the test runs a copy of itself that crashes and watches it.
+#[test]
+pub fn unhandled_exceptions_are_reported() {
+  if let Ok(port) = env::var(CRASH_PORT) {
+    let sentry = local_sentry(port.parse().unwrap());
+    seh::register_crash_handler(Arc::new(sentry), Duration::from_secs(5));
+    unsafe {
+      std::ptr::write_volatile(std::ptr::null_mut::<u8>(), 1);
+    }
+    unreachable!();
+  }
+
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let port = listener.local_addr().unwrap().port();
+  let served = serve_events(listener);
+  let mut child = Command::new(env::current_exe().unwrap())
+    .args(&["--exact", "unhandled_exceptions_are_reported", "--test-threads", "1"])
+    .env(CRASH_PORT, port.to_string())
+    .spawn()
+    .unwrap();
+
+  let event = served.recv_timeout(Duration::from_secs(10)).unwrap();
+  assert_eq!(event["level"], json!("fatal"));
+  assert_eq!(event["tags"]["exception_code"], json!("EXCEPTION_ACCESS_VIOLATION"));
+  let mechanism = &event["exception"]["values"][0]["mechanism"];
+  assert_eq!(mechanism["type"], json!("UnhandledExceptionFilter"));
+  assert_eq!(mechanism["handled"], json!(false));
+  assert_eq!(mechanism["data"]["code"], json!("0xC0000005"));
+  assert_eq!(mechanism["data"]["operation"], json!("write"));
+  assert_eq!(mechanism["data"]["fault_address"], json!("0x0"));
+  assert_eq!(event["contexts"]["os"]["name"], json!("Windows"));
+  assert!(event["contexts"]["os"]["build"].is_string());
+  assert!(!event["stacktrace"]["frames"].as_array().unwrap().is_empty());
+
+  let status = child.wait().unwrap();
+  assert_eq!(status.code().map(|code| code as u32), Some(seh::EXCEPTION_ACCESS_VIOLATION));
+}
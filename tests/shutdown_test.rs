@@ -0,0 +1,50 @@
+extern crate futures;
+extern crate sentry_rs;
+extern crate serde_json;
+
+mod common;
+
+use common::local_sentry;
+use futures::Future;
+use sentry_rs::error::CaptureError;
+use sentry_rs::reactor;
+
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread;
+use std::time::Duration;
+
+/// Accepts `count` events, one connection each.
+pub fn serve(listener: TcpListener, count: usize) {
+  thread::spawn(move || {
+    for stream in listener.incoming().take(count) {
+      let mut stream = stream.unwrap();
+      let mut request = [0; 65536];
+      let _ = stream.read(&mut request);
+      let _ = write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+    }
+  });
+}
+
+// The event loop is shared by the whole process, so shutting it down lives in its own test binary.
+#[test]
+pub fn close_and_shutdown_stop_everything() {
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let port = listener.local_addr().unwrap().port();
+  serve(listener, 2);
+
+  let sentry = local_sentry(port);
+  let event = sentry.create_event("test", "error", "Before close", None, None, None);
+  let sent = sentry.capture_event(event);
+  assert!(sentry.close(Duration::from_secs(5)));
+  assert!(sent.wait().is_ok());
+
+  let event = sentry.create_event("test", "error", "After close", None, None, None);
+  assert_eq!(sentry.capture_event(event).wait(), Err(CaptureError::Closed));
+  reactor::shutdown();
+
+  // A client created afterwards starts the event loop again.
+  let sentry = local_sentry(port);
+  let event = sentry.create_event("test", "error", "After shutdown", None, None, None);
+  assert!(sentry.capture_event(event).wait().is_ok());
+}
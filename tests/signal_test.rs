@@ -0,0 +1,93 @@
+#![cfg(unix)]
+
+extern crate libc;
+extern crate sentry_rs;
+#[macro_use]
+extern crate serde_json;
+
+mod common;
+
+use common::{local_sentry, serve_events};
+use sentry_rs::integrations::signal;
+
+use std::env;
+use std::hint;
+use std::net::TcpListener;
+use std::os::unix::process::ExitStatusExt;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Set for the copy of this test binary that crashes, to the port to report the crash to.
+const CRASH_PORT: &'static str = "SENTRY_RS_TEST_CRASH_PORT";
+/// Set for the copy of this test binary that overflows its stack, to the port to report the crash to.
+const OVERFLOW_PORT: &'static str = "SENTRY_RS_TEST_OVERFLOW_PORT";
+
+// Crashing takes the process down, so the test runs a copy of itself that crashes and watches it.
+#[test]
+pub fn fatal_signals_are_reported() {
+  if let Ok(port) = env::var(CRASH_PORT) {
+    let sentry = local_sentry(port.parse().unwrap());
+    signal::register_crash_handler(Arc::new(sentry), Duration::from_secs(5));
+    // A real fault rather than `raise`, so the fault happens again once the signal is passed on.
+    unsafe {
+      std::ptr::write_volatile(std::ptr::null_mut::<u8>(), 1);
+    }
+    unreachable!();
+  }
+
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let port = listener.local_addr().unwrap().port();
+  let served = serve_events(listener);
+  let mut child = Command::new(env::current_exe().unwrap())
+    .args(&["--exact", "fatal_signals_are_reported", "--test-threads", "1"])
+    .env(CRASH_PORT, port.to_string())
+    .spawn()
+    .unwrap();
+
+  let event = served.recv_timeout(Duration::from_secs(10)).unwrap();
+  assert_eq!(event["level"], json!("fatal"));
+  assert_eq!(event["tags"]["signal"], json!("SIGSEGV"));
+  let mechanism = &event["exception"]["values"][0]["mechanism"];
+  assert_eq!(mechanism["type"], json!("signal"));
+  assert_eq!(mechanism["handled"], json!(false));
+  assert_eq!(mechanism["meta"]["signal"], json!({"number": libc::SIGSEGV, "name": "SIGSEGV"}));
+  assert!(event["message"].as_str().unwrap().starts_with("Fatal signal SIGSEGV"));
+  assert!(event["breadcrumbs"].is_null());
+
+  let status = child.wait().unwrap();
+  assert_eq!(status.signal(), Some(libc::SIGSEGV));
+}
+
+#[allow(unconditional_recursion)]
+fn overflow(depth: u64) -> u64 {
+  let frame = hint::black_box([depth; 64]);
+  overflow(frame[0] + 1) + frame[1]
+}
+
+#[test]
+pub fn stack_overflows_are_reported_and_passed_on() {
+  if let Ok(port) = env::var(OVERFLOW_PORT) {
+    let sentry = local_sentry(port.parse().unwrap());
+    signal::register_crash_handler(Arc::new(sentry), Duration::from_secs(5));
+    overflow(0);
+    unreachable!();
+  }
+
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let port = listener.local_addr().unwrap().port();
+  let served = serve_events(listener);
+  let child = Command::new(env::current_exe().unwrap())
+    .args(&["--exact", "stack_overflows_are_reported_and_passed_on", "--test-threads", "1"])
+    .env(OVERFLOW_PORT, port.to_string())
+    .stderr(Stdio::piped())
+    .spawn()
+    .unwrap();
+
+  let event = served.recv_timeout(Duration::from_secs(10)).unwrap();
+  assert_eq!(event["tags"]["signal"], json!("SIGSEGV"));
+  // Passed on to the standard library's handler, which says what happened and aborts.
+  let output = child.wait_with_output().unwrap();
+  assert!(String::from_utf8_lossy(&output.stderr).contains("has overflowed its stack"));
+  assert_eq!(output.status.signal(), Some(libc::SIGABRT));
+}
@@ -0,0 +1,50 @@
+#![cfg(feature = "slog")]
+
+extern crate sentry_rs;
+#[macro_use]
+extern crate serde_json;
+#[macro_use]
+extern crate slog;
+
+mod common;
+
+use common::{local_sentry, serve_events};
+use sentry_rs::integrations::slog::SentryDrain;
+use slog::Drain;
+
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[test]
+pub fn records_become_events_or_breadcrumbs_by_level() {
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let sentry = Arc::new(local_sentry(listener.local_addr().unwrap().port()));
+  let served = serve_events(listener);
+  let mut drain = SentryDrain::new(sentry, slog::Level::Error);
+  drain.breadcrumb_level = Some(slog::Level::Info);
+  drain.tag_keys.push("user".to_owned());
+  let logger = slog::Logger::root(Mutex::new(drain).fuse(), o!("service" => "billing"));
+
+  debug!(logger, "Too quiet for either");
+  info!(logger, "Cache warmed"; "entries" => 12);
+  error!(logger, "Charge failed"; "user" => "alice", "amount" => 42);
+
+  let event = served.recv_timeout(Duration::from_secs(5)).unwrap();
+  assert_eq!(event["message"], json!("Charge failed"));
+  assert_eq!(event["level"], json!("error"));
+  assert_eq!(event["logger"], json!(module_path!()));
+  // Keys in `tag_keys` become tags, and the rest, from the record and the logger alike, extras.
+  assert_eq!(event["tags"]["user"], json!("alice"));
+  assert!(event["extra"]["user"].is_null());
+  assert_eq!(event["extra"]["amount"], json!(42));
+  assert_eq!(event["extra"]["service"], json!("billing"));
+
+  let breadcrumbs = event["breadcrumbs"]["values"].as_array().unwrap();
+  assert_eq!(breadcrumbs.len(), 1);
+  assert_eq!(breadcrumbs[0]["message"], json!("Cache warmed"));
+  assert_eq!(breadcrumbs[0]["level"], json!("info"));
+  assert_eq!(breadcrumbs[0]["data"]["entries"], json!(12));
+  assert_eq!(breadcrumbs[0]["data"]["service"], json!("billing"));
+  assert!(served.recv_timeout(Duration::from_millis(200)).is_err());
+}
@@ -0,0 +1,54 @@
+#![cfg(feature = "tracing")]
+
+extern crate sentry_rs;
+#[macro_use]
+extern crate serde_json;
+#[macro_use]
+extern crate tracing;
+extern crate tracing_subscriber;
+
+mod common;
+
+use common::{local_sentry, serve_events};
+use sentry_rs::integrations::tracing::SentryLayer;
+use tracing::Level;
+use tracing_subscriber::layer::SubscriberExt;
+
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[test]
+pub fn events_and_breadcrumbs_follow_their_thresholds() {
+  let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+  let sentry = Arc::new(local_sentry(listener.local_addr().unwrap().port()));
+  let served = serve_events(listener);
+  let mut layer = SentryLayer::new(sentry);
+  layer.level = Level::WARN;
+
+  tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+    debug!("Too quiet for either");
+    let span = info_span!("checkout", cart = "c-42", user = "alice");
+    let _entered = span.enter();
+    info!(items = 3, "Cart loaded");
+    warn!(retries = 2, "Payment slow");
+  });
+
+  let event = served.recv_timeout(Duration::from_secs(5)).unwrap();
+  assert_eq!(event["message"], json!("Payment slow"));
+  assert_eq!(event["level"], json!("warning"));
+  assert_eq!(event["logger"], json!(module_path!()));
+  assert_eq!(event["extra"]["retries"], json!(2));
+  // The fields of the spans the event happened in become its tags.
+  assert_eq!(event["tags"]["cart"], json!("c-42"));
+  assert_eq!(event["tags"]["user"], json!("alice"));
+
+  let breadcrumbs = event["breadcrumbs"]["values"].as_array().unwrap();
+  assert_eq!(breadcrumbs.len(), 2);
+  assert_eq!(breadcrumbs[0]["message"], json!("checkout"));
+  assert_eq!(breadcrumbs[0]["data"]["cart"], json!("c-42"));
+  assert_eq!(breadcrumbs[1]["message"], json!("Cart loaded"));
+  assert_eq!(breadcrumbs[1]["level"], json!("info"));
+  assert_eq!(breadcrumbs[1]["data"]["items"], json!(3));
+  assert!(served.recv_timeout(Duration::from_millis(200)).is_err());
+}
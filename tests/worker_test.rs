@@ -0,0 +1,294 @@
+extern crate sentry_rs;
+
+use sentry_rs::error::CaptureError;
+use sentry_rs::workers::single::SingleWorker;
+
+use std::sync::mpsc::channel;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[test]
+pub fn worker_restarts_after_panic() {
+  let (sender, receiver) = channel::<u32>();
+  let sender = Arc::new(Mutex::new(sender));
+  let worker = SingleWorker::new(
+    (),
+    Box::new(move |_: &(), value: u32| {
+      if value == 0 {
+        panic!("Worker closure panicked");
+      }
+      sender.lock().unwrap().send(value).unwrap();
+    }),
+  );
+
+  worker.work_with(0).unwrap();
+  worker.work_with(1).unwrap();
+  worker.work_with(2).unwrap();
+
+  assert_eq!(receiver.recv_timeout(Duration::from_secs(5)).unwrap(), 1);
+  assert_eq!(receiver.recv_timeout(Duration::from_secs(5)).unwrap(), 2);
+  assert_eq!(worker.restarts(), 1);
+}
+
+#[test]
+pub fn worker_carries_on_straight_after_a_panic() {
+  let (sender, receiver) = channel::<u32>();
+  let sender = Arc::new(Mutex::new(sender));
+  let worker = SingleWorker::new(
+    (),
+    Box::new(move |_: &(), value: u32| {
+      if value == 0 {
+        panic!("Worker closure panicked");
+      }
+      sender.lock().unwrap().send(value).unwrap();
+    }),
+  );
+
+  let started = Instant::now();
+  for _ in 0..5 {
+    worker.work_with(0).unwrap();
+  }
+  worker.work_ahead(1).unwrap();
+  worker.work_with(2).unwrap();
+
+  assert_eq!(receiver.recv_timeout(Duration::from_secs(5)).unwrap(), 1);
+  assert_eq!(receiver.recv_timeout(Duration::from_secs(5)).unwrap(), 2);
+  assert!(started.elapsed() < Duration::from_secs(1), "{:?}", started.elapsed());
+  assert_eq!(worker.restarts(), 5);
+}
+
+#[test]
+pub fn worker_finishes_queued_items_on_shutdown() {
+  let (sender, receiver) = channel::<u32>();
+  let sender = Arc::new(Mutex::new(sender));
+  let worker = SingleWorker::new(
+    (),
+    Box::new(move |_: &(), value: u32| {
+      thread::sleep(Duration::from_millis(50));
+      sender.lock().unwrap().send(value).unwrap();
+    }),
+  );
+
+  worker.work_with(1).unwrap();
+  worker.work_with(2).unwrap();
+  assert!(worker.shutdown(Duration::from_secs(5)));
+  assert_eq!(worker.work_with(3), Err(CaptureError::Closed));
+
+  assert_eq!(receiver.try_iter().collect::<Vec<_>>(), vec![1, 2]);
+}
+
+#[test]
+pub fn worker_refuses_items_past_capacity() {
+  let (sender, receiver) = channel::<()>();
+  let receiver = Arc::new(Mutex::new(receiver));
+  let worker = SingleWorker::with_capacity(
+    (),
+    Box::new(move |_: &(), _: u32| {
+      receiver.lock().unwrap().recv_timeout(Duration::from_secs(5)).unwrap();
+    }),
+    2,
+  );
+
+  worker.work_with(1).unwrap();
+  worker.work_with(2).unwrap();
+  assert_eq!(worker.work_with(3), Err(CaptureError::QueueFull));
+
+  sender.send(()).unwrap();
+  sender.send(()).unwrap();
+  assert!(worker.shutdown(Duration::from_secs(5)));
+  assert_eq!(worker.pending(), 0);
+}
+
+#[test]
+pub fn urgent_items_go_ahead_of_the_backlog() {
+  let (gate, gate_receiver) = channel::<()>();
+  let gate_receiver = Arc::new(Mutex::new(gate_receiver));
+  let (started, started_receiver) = channel::<u32>();
+  let started = Arc::new(Mutex::new(started));
+  let (sender, receiver) = channel::<u32>();
+  let sender = Arc::new(Mutex::new(sender));
+  let worker = SingleWorker::with_capacity(
+    (),
+    Box::new(move |_: &(), value: u32| {
+      started.lock().unwrap().send(value).unwrap();
+      gate_receiver.lock().unwrap().recv_timeout(Duration::from_secs(5)).unwrap();
+      sender.lock().unwrap().send(value).unwrap();
+    }),
+    3,
+  );
+
+  // The first item is being worked on, so everything after it waits.
+  worker.work_with(1).unwrap();
+  assert_eq!(started_receiver.recv_timeout(Duration::from_secs(5)).unwrap(), 1);
+  worker.work_with(2).unwrap();
+  worker.work_with(3).unwrap();
+  assert_eq!(worker.work_with(4), Err(CaptureError::QueueFull));
+  worker.work_ahead(9).unwrap();
+
+  for _ in 0..4 {
+    gate.send(()).unwrap();
+  }
+  let order = (0..4)
+    .map(|_| receiver.recv_timeout(Duration::from_secs(5)).unwrap())
+    .collect::<Vec<_>>();
+  assert_eq!(order, vec![1, 9, 2, 3]);
+}
+
+#[test]
+pub fn worker_spawns_lazily_and_exits_when_idle() {
+  let (sender, receiver) = channel::<u32>();
+  let sender = Arc::new(Mutex::new(sender));
+  let worker = SingleWorker::new(
+    (),
+    Box::new(move |_: &(), value: u32| {
+      sender.lock().unwrap().send(value).unwrap();
+    }),
+  );
+  assert!(!worker.is_alive());
+  worker.set_idle_timeout(Some(Duration::from_millis(20)));
+
+  worker.work_with(1).unwrap();
+  assert_eq!(receiver.recv_timeout(Duration::from_secs(5)).unwrap(), 1);
+  let deadline = Instant::now() + Duration::from_secs(5);
+  while worker.is_alive() && Instant::now() < deadline {
+    thread::sleep(Duration::from_millis(5));
+  }
+  assert!(!worker.is_alive());
+
+  worker.work_with(2).unwrap();
+  assert_eq!(receiver.recv_timeout(Duration::from_secs(5)).unwrap(), 2);
+}
+
+#[test]
+pub fn items_sent_while_the_worker_exits_are_not_lost() {
+  let (sender, receiver) = channel::<u32>();
+  let sender = Arc::new(Mutex::new(sender));
+  let worker = Arc::new(SingleWorker::new(
+    (),
+    Box::new(move |_: &(), value: u32| {
+      sender.lock().unwrap().send(value).unwrap();
+    }),
+  ));
+  // Short enough that the thread is forever exiting just as the next item arrives.
+  worker.set_idle_timeout(Some(Duration::from_millis(1)));
+
+  let senders = (0..4)
+    .map(|thread_index| {
+      let worker = worker.clone();
+      thread::spawn(move || {
+        for i in 0..100 {
+          worker.work_with(thread_index * 100 + i).unwrap();
+          thread::sleep(Duration::from_micros((i as u64 % 3) * 700));
+        }
+      })
+    })
+    .collect::<Vec<_>>();
+  for sending in senders {
+    sending.join().unwrap();
+  }
+
+  let mut received = (0..400)
+    .map(|_| receiver.recv_timeout(Duration::from_secs(5)).unwrap())
+    .collect::<Vec<_>>();
+  received.sort();
+  assert_eq!(received, (0..400).collect::<Vec<_>>());
+  assert!(worker.shutdown(Duration::from_secs(5)));
+}
+
+#[test]
+pub fn worker_runs_on_its_own_named_thread() {
+  let (sender, receiver) = channel::<Option<String>>();
+  let sender = Arc::new(Mutex::new(sender));
+  let worker = SingleWorker::new(
+    (),
+    Box::new(move |_: &(), _: u32| {
+      let name = thread::current().name().map(|name| name.to_owned());
+      sender.lock().unwrap().send(name).unwrap();
+    }),
+  );
+  worker.set_spawn_timeout(Duration::from_millis(100));
+
+  worker.work_with(1).unwrap();
+  assert_eq!(
+    receiver.recv_timeout(Duration::from_secs(5)).unwrap(),
+    Some("sentry-worker".to_owned())
+  );
+  assert!(worker.is_alive());
+}
+
+#[test]
+pub fn items_sent_in_a_burst_from_many_threads_all_arrive() {
+  let (sender, receiver) = channel::<u32>();
+  let sender = Arc::new(Mutex::new(sender));
+  let worker = Arc::new(SingleWorker::new(
+    (),
+    Box::new(move |_: &(), value: u32| {
+      sender.lock().unwrap().send(value).unwrap();
+    }),
+  ));
+
+  let senders = (0..8)
+    .map(|thread_index| {
+      let worker = worker.clone();
+      thread::spawn(move || {
+        for i in 0..1000 {
+          let value = thread_index * 1000 + i;
+          if i % 10 == 0 {
+            worker.work_ahead(value).unwrap();
+          } else {
+            worker.work_with(value).unwrap();
+          }
+        }
+      })
+    })
+    .collect::<Vec<_>>();
+  for sending in senders {
+    sending.join().unwrap();
+  }
+
+  let mut received = (0..8000)
+    .map(|_| receiver.recv_timeout(Duration::from_secs(5)).unwrap())
+    .collect::<Vec<_>>();
+  received.sort();
+  assert_eq!(received, (0..8000).collect::<Vec<_>>());
+  assert!(worker.shutdown(Duration::from_secs(5)));
+  assert_eq!(worker.pending(), 0);
+}
+
+#[test]
+pub fn paused_workers_hold_items_until_resumed() {
+  let (sender, receiver) = channel::<u32>();
+  let sender = Arc::new(Mutex::new(sender));
+  let worker = SingleWorker::new(
+    (),
+    Box::new(move |_: &(), value: u32| {
+      sender.lock().unwrap().send(value).unwrap();
+    }),
+  );
+  // Pausing keeps the thread around, so it's there to resume.
+  worker.set_idle_timeout(Some(Duration::from_millis(1)));
+
+  worker.pause();
+  assert!(worker.is_paused());
+  for i in 0..3 {
+    worker.work_with(i).unwrap();
+  }
+  assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err());
+  assert!(worker.is_alive());
+  assert_eq!(worker.pending(), 3);
+
+  worker.resume();
+  let received = (0..3)
+    .map(|_| receiver.recv_timeout(Duration::from_secs(5)).unwrap())
+    .collect::<Vec<_>>();
+  assert_eq!(received, vec![0, 1, 2]);
+
+  // Shutting down resumes a paused worker, so what's queued isn't stranded.
+  worker.pause();
+  worker.work_with(3).unwrap();
+  assert!(worker.shutdown(Duration::from_secs(5)));
+  assert_eq!(receiver.recv_timeout(Duration::from_secs(1)).unwrap(), 3);
+  worker.pause();
+  assert!(!worker.is_paused());
+}